@@ -0,0 +1,360 @@
+//! Tracks which route badges each rider has earned.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    insider::{find_route, Route, Sport},
+    world::World,
+};
+
+/// The routes each rider has completed, keyed by sport and the route names
+/// in the scraped routes table. Saved as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BadgeLedger {
+    riders: BTreeMap<String, BTreeSet<Completion>>,
+}
+
+/// A completed route. Cycling and running routes can share a name, so the
+/// sport is part of the key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "CompletionEntry")]
+struct Completion {
+    sport: Sport,
+    name: String,
+}
+
+/// A completion as saved. Ledgers saved before sports were recorded list
+/// route names only, which are cycling routes.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CompletionEntry {
+    Name(String),
+    Completion {
+        #[serde(default = "cycling")]
+        sport: Sport,
+        name: String,
+    },
+}
+
+fn cycling() -> Sport {
+    Sport::Cycling
+}
+
+impl From<CompletionEntry> for Completion {
+    fn from(entry: CompletionEntry) -> Self {
+        match entry {
+            CompletionEntry::Name(name) => Completion {
+                sport: cycling(),
+                name,
+            },
+            CompletionEntry::Completion { sport, name } => Completion { sport, name },
+        }
+    }
+}
+
+impl BadgeLedger {
+    /// Loads a ledger, returning an empty one if `path` doesn't exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read badge ledger {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Invalid badge ledger {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write badge ledger {}", path.display()))
+    }
+
+    pub fn riders(&self) -> impl Iterator<Item = &str> {
+        self.riders.keys().map(String::as_str)
+    }
+
+    /// Records that `rider` completed `route`. Returns false if it was already
+    /// recorded.
+    pub fn record(&mut self, rider: &str, route: &Route) -> bool {
+        self.riders
+            .entry(rider.to_string())
+            .or_default()
+            .insert(Completion {
+                sport: route.sport,
+                name: route.name.clone(),
+            })
+    }
+
+    /// Removes a completion recorded by mistake. Returns false if it wasn't
+    /// recorded.
+    pub fn remove(&mut self, rider: &str, sport: Sport, route_name: &str) -> bool {
+        self.riders.get_mut(rider).is_some_and(|routes| {
+            routes.remove(&Completion {
+                sport,
+                name: route_name.to_string(),
+            })
+        })
+    }
+
+    /// The names of the `sport` routes `rider` has completed, in alphabetical
+    /// order.
+    pub fn completed(&self, rider: &str, sport: Sport) -> Vec<&str> {
+        self.completions(rider)
+            .filter(|completion| completion.sport == sport)
+            .map(|completion| completion.name.as_str())
+            .collect()
+    }
+
+    pub fn has_completed(&self, rider: &str, route: &Route) -> bool {
+        self.completions(rider).any(|completion| {
+            completion.sport == route.sport && route.matches_name(&completion.name)
+        })
+    }
+
+    /// The completions of `rider`, cycling first and then by name.
+    fn completions(&self, rider: &str) -> impl Iterator<Item = &Completion> {
+        self.riders.get(rider).into_iter().flatten()
+    }
+
+    /// The routes `rider` hasn't completed yet.
+    pub fn remaining<'a>(&self, rider: &str, routes: &'a [Route]) -> Vec<&'a Route> {
+        routes
+            .iter()
+            .filter(|route| !self.has_completed(rider, route))
+            .collect()
+    }
+
+    /// The routes `rider` hasn't completed yet, grouped by world. Routes in
+    /// an unknown world are grouped under `None`.
+    pub fn remaining_by_world<'a>(
+        &self,
+        rider: &str,
+        routes: &'a [Route],
+    ) -> BTreeMap<Option<World>, Vec<&'a Route>> {
+        let mut by_world: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for route in self.remaining(rider, routes) {
+            by_world.entry(route.world).or_default().push(route);
+        }
+        by_world
+    }
+
+    /// The badge XP `rider` can still earn from `routes`.
+    pub fn remaining_xp(&self, rider: &str, routes: &[Route]) -> u32 {
+        self.remaining(rider, routes)
+            .iter()
+            .filter_map(|route| route.badge_xp)
+            .sum()
+    }
+
+    /// Records the routes listed in `text`, one per line. The first comma
+    /// separated field of each line is the route name and a later field of
+    /// "Cycling" or "Running" the sport, so the `zwift-routes routes` output
+    /// can be filtered and imported. Names are matched against `routes`, of
+    /// any sport if the line doesn't name one, and those that don't match any
+    /// route are returned.
+    pub fn import(&mut self, rider: &str, text: &str, routes: &[Route]) -> Vec<String> {
+        let mut unmatched = Vec::new();
+        for line in text.lines() {
+            let Some(name) = route_names(line).next() else {
+                continue;
+            };
+            let route = match line_sport(line) {
+                Some(sport) => routes
+                    .iter()
+                    .find(|route| route.sport == sport && route.matches_name(name)),
+                None => find_route(routes, name),
+            };
+            match route {
+                Some(route) => {
+                    self.record(rider, route);
+                }
+                None => {
+                    warn!("No route called \"{name}\"");
+                    unmatched.push(name.to_string());
+                }
+            }
+        }
+        unmatched
+    }
+
+    /// The routes `rider` has completed with their sport, one per line, in
+    /// the format read by [`BadgeLedger::import`].
+    pub fn export(&self, rider: &str) -> String {
+        self.completions(rider)
+            .map(|completion| format!("{},{}\n", completion.name, completion.sport))
+            .collect()
+    }
+
+    /// Adds every completion recorded in `other`.
+    pub fn merge(&mut self, other: &BadgeLedger) {
+        for (rider, routes) in &other.riders {
+            self.riders
+                .entry(rider.clone())
+                .or_default()
+                .extend(routes.iter().cloned());
+        }
+    }
+}
+
+/// The sport named by one of the fields after the first in a comma
+/// separated line.
+fn line_sport(line: &str) -> Option<Sport> {
+    line.split(',').skip(1).find_map(|field| {
+        [Sport::Cycling, Sport::Running]
+            .into_iter()
+            .find(|sport| field.trim().eq_ignore_ascii_case(&sport.to_string()))
+    })
+}
+
+/// The route names in a list of routes, one per line, where only the first
+/// comma separated field of each line is used.
+pub(crate) fn route_names(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.split(',').next().unwrap_or_default().trim())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::Dom;
+
+    use super::BadgeLedger;
+    use crate::{
+        insider::{parse_routes, parse_running_routes, Route, Sport},
+        world::World,
+    };
+
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+    static RUNNING_ROUTES_HTML: &str = include_str!("../fixtures/insider/running-routes.html");
+
+    async fn routes() -> Vec<Route> {
+        parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn running_routes() -> Vec<Route> {
+        parse_running_routes(&Dom::parse(RUNNING_ROUTES_HTML).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_queries() {
+        let routes = routes().await;
+        let mut ledger = BadgeLedger::default();
+        assert!(ledger.record("alice", &routes[0]));
+        assert!(!ledger.record("alice", &routes[0]));
+        ledger.record("alice", &routes[1]);
+        ledger.record("bob", &routes[2]);
+
+        assert_eq!(ledger.riders().collect::<Vec<_>>(), ["alice", "bob"]);
+        assert_eq!(
+            ledger.completed("alice", Sport::Cycling),
+            ["Road to Sky", "Tempus Fugit"]
+        );
+        assert!(ledger.has_completed("alice", &routes[1]));
+        assert!(!ledger.has_completed("bob", &routes[1]));
+        assert!(ledger.completed("carol", Sport::Cycling).is_empty());
+
+        // Tempus Fugit is both a cycling and a running route
+        let running_routes = running_routes().await;
+        let run = running_routes
+            .iter()
+            .find(|route| route.name == routes[0].name)
+            .unwrap();
+        assert!(!ledger.has_completed("alice", run));
+        assert!(ledger.record("alice", run));
+        assert!(ledger.has_completed("alice", run));
+        assert_eq!(ledger.completed("alice", Sport::Running), ["Tempus Fugit"]);
+        assert!(ledger.remove("alice", Sport::Running, "Tempus Fugit"));
+        assert!(ledger.has_completed("alice", &routes[0]));
+        assert!(!ledger.has_completed("alice", run));
+
+        let remaining = ledger.remaining("alice", &routes);
+        assert_eq!(remaining.len(), routes.len() - 2);
+        let total_xp: u32 = routes.iter().filter_map(|route| route.badge_xp).sum();
+        let completed_xp = routes[0].badge_xp.unwrap() + routes[1].badge_xp.unwrap();
+        assert_eq!(
+            ledger.remaining_xp("alice", &routes),
+            total_xp - completed_xp
+        );
+
+        let by_world = ledger.remaining_by_world("alice", &routes);
+        assert!(by_world[&Some(World::Watopia)]
+            .iter()
+            .all(|route| route.world == Some(World::Watopia)));
+        assert!(by_world[&Some(World::MakuriIslands)]
+            .iter()
+            .any(|route| route.name == "Castle to Castle"));
+
+        assert!(ledger.remove("alice", Sport::Cycling, "Road to Sky"));
+        assert!(!ledger.remove("alice", Sport::Cycling, "Road to Sky"));
+        assert!(!ledger.has_completed("alice", &routes[1]));
+    }
+
+    #[tokio::test]
+    async fn test_import_export() {
+        let routes = routes().await;
+        let mut ledger = BadgeLedger::default();
+        let unmatched = ledger.import(
+            "alice",
+            "Route,World\ntour of fire and ice,Watopia\n\nTempus Fugit\nMystery Loop\n",
+            &routes,
+        );
+        assert_eq!(unmatched, ["Route", "Mystery Loop"]);
+        assert_eq!(
+            ledger.export("alice"),
+            "Tempus Fugit,Cycling\nTour of Fire & Ice,Cycling\n"
+        );
+
+        let mut all_routes = routes.clone();
+        all_routes.extend(running_routes().await);
+        let mut other = BadgeLedger::default();
+        other.import("alice", &ledger.export("alice"), &all_routes);
+        let unmatched = other.import(
+            "bob",
+            "Tempus Fugit,Running\nRoad to Sky,Running\n",
+            &all_routes,
+        );
+        assert_eq!(unmatched, ["Road to Sky"]);
+        other.record("bob", &routes[1]);
+        ledger.merge(&other);
+        assert_eq!(ledger.completed("alice", Sport::Cycling).len(), 2);
+        assert!(ledger.completed("alice", Sport::Running).is_empty());
+        assert_eq!(ledger.completed("bob", Sport::Cycling), ["Road to Sky"]);
+        assert_eq!(ledger.completed("bob", Sport::Running), ["Tempus Fugit"]);
+    }
+
+    #[tokio::test]
+    async fn test_save_load() {
+        let routes = routes().await;
+        let path = std::env::temp_dir().join(format!("badges-{}.json", std::process::id()));
+        assert_eq!(BadgeLedger::load(&path).unwrap(), BadgeLedger::default());
+
+        let mut ledger = BadgeLedger::default();
+        ledger.record("alice", &routes[3]);
+        ledger.save(&path).unwrap();
+        let loaded = BadgeLedger::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, ledger);
+
+        // Ledgers saved before sports were recorded hold cycling routes
+        let old: BadgeLedger =
+            serde_json::from_str(r#"{"riders": {"alice": ["Road to Sky"]}}"#).unwrap();
+        assert!(old.has_completed("alice", &routes[1]));
+        let partial: BadgeLedger =
+            serde_json::from_str(r#"{"riders": {"alice": [{"name": "Road to Sky"}]}}"#).unwrap();
+        assert_eq!(partial, old);
+    }
+}
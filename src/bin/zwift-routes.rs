@@ -1,67 +1,774 @@
-use anyhow::Result;
-use clap::Parser;
-use env_logger::Builder;
-use html_parser::{Dom, Node};
-use log::{debug, LevelFilter};
-use zwift_data::html_query;
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-async fn download_webpage(url: &str) -> Result<String> {
-    debug!("Downloading web page {url}...");
-    Ok(reqwest::get(url).await?.text().await?)
-}
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use env_logger::Builder;
+use html_parser::Dom;
+use log::{info, warn, LevelFilter};
+use zwift_data::{
+    catalogue::WorkoutCatalogue,
+    diff, events,
+    export::{self, ExportFormat},
+    html_query,
+    insider::{self, download_webpage, Route, Sport},
+    physics::Rider,
+    progression::{self, PlanActivity, Progression},
+    recommend,
+    snapshot::{RouteSnapshot, SnapshotStore},
+    workout::{self, Workout, Zone},
+    UnitSystem,
+};
 
 #[derive(Parser, Debug)]
 struct Args {
-    /// Routes web page
-    web_page: String,
+    #[command(subcommand)]
+    command: Command,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let mut builder = Builder::from_default_env();
-    builder.format_timestamp_micros().init();
-    builder.filter_level(LevelFilter::Debug);
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the routes table as comma separated rows
+    Routes {
+        /// Routes web page
+        #[arg(default_value = insider::ROUTES_URL)]
+        web_page: String,
+        /// Running routes web page
+        #[arg(long, default_value = insider::RUNNING_ROUTES_URL)]
+        running_page: String,
+        /// Only print routes for this sport: cycling or running. Prints both by
+        /// default
+        #[arg(long)]
+        sport: Option<Sport>,
+        /// Units for distances and elevations: metric or imperial
+        #[arg(long, default_value = "metric")]
+        units: UnitSystem,
+    },
+    /// Recommend uncompleted routes that can be ridden in a target time
+    Recommend {
+        /// Routes web page
+        #[arg(default_value = insider::ROUTES_URL)]
+        web_page: String,
+        /// Shortest ride time in minutes
+        #[arg(long, default_value_t = 60)]
+        min_minutes: u64,
+        /// Longest ride time in minutes
+        #[arg(long, default_value_t = 75)]
+        max_minutes: u64,
+        /// Rider power in W/kg
+        #[arg(long, default_value_t = 3.0)]
+        wkg: f64,
+        /// Rider weight in kg
+        #[arg(long, default_value_t = 75.0)]
+        weight: f64,
+        /// File listing completed routes, one per line
+        #[arg(long)]
+        completed: Option<PathBuf>,
+        /// Units for distances and elevations: metric or imperial
+        #[arg(long, default_value = "metric")]
+        units: UnitSystem,
+    },
+    /// Find routes that finish, lead-in included, when a workout ends,
+    /// most badge XP first
+    FitWorkout {
+        /// Workout length in minutes
+        #[arg(long, required_unless_present = "zwo", conflicts_with = "zwo")]
+        minutes: Option<u64>,
+        /// Workout file, ridden in ERG mode at its average power
+        #[arg(long)]
+        zwo: Option<PathBuf>,
+        /// Rider power in W/kg, when given a workout length
+        #[arg(long, default_value_t = 3.0)]
+        wkg: f64,
+        /// Rider FTP in W, when given a workout file
+        #[arg(long, default_value_t = 250.0)]
+        ftp: f64,
+        /// Rider weight in kg
+        #[arg(long, default_value_t = 75.0)]
+        weight: f64,
+        /// How far from the end of the workout a route may finish, in minutes
+        #[arg(long, default_value_t = 5)]
+        tolerance_minutes: u64,
+        /// Routes web page
+        #[arg(long, default_value = insider::ROUTES_URL)]
+        routes_page: String,
+        /// Units for distances and elevations: metric or imperial
+        #[arg(long, default_value = "metric")]
+        units: UnitSystem,
+    },
+    /// Plan the route badges and distance to reach a level fastest
+    PlanLevel {
+        /// Current XP
+        #[arg(long)]
+        xp: u32,
+        /// Level to reach
+        #[arg(long)]
+        target_level: u32,
+        /// Hours of riding a week
+        #[arg(long, default_value_t = 5.0)]
+        weekly_hours: f64,
+        /// Rider power in W/kg
+        #[arg(long, default_value_t = 3.0)]
+        wkg: f64,
+        /// Rider weight in kg
+        #[arg(long, default_value_t = 75.0)]
+        weight: f64,
+        /// File listing completed routes, one per line
+        #[arg(long)]
+        completed: Option<PathBuf>,
+        /// Routes web page
+        #[arg(long, default_value = insider::ROUTES_URL)]
+        routes_page: String,
+        /// Units for distances: metric or imperial
+        #[arg(long, default_value = "metric")]
+        units: UnitSystem,
+    },
+    /// List the routes that can be ridden on a date, given the guest world calendar
+    Rideable {
+        /// Date to check, e.g. 2026-10-18. Defaults to today
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Routes web page
+        #[arg(long, default_value = insider::ROUTES_URL)]
+        routes_page: String,
+        /// Guest world calendar web page
+        #[arg(long, default_value = insider::GUEST_WORLD_CALENDAR_URL)]
+        calendar_page: String,
+    },
+    /// Save the routes table to a snapshot directory for comparing later
+    Snapshot {
+        /// Directory to save the snapshot in
+        #[arg(long, default_value = "snapshots")]
+        dir: PathBuf,
+        /// Routes web page
+        #[arg(default_value = insider::ROUTES_URL)]
+        web_page: String,
+        /// Running routes web page
+        #[arg(long, default_value = insider::RUNNING_ROUTES_URL)]
+        running_page: String,
+    },
+    /// Print the routes added, removed and changed between two snapshots
+    Diff {
+        /// Older snapshot file
+        old: PathBuf,
+        /// Newer snapshot file
+        new: PathBuf,
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the route, distance, elevation and badge XP of each event in an
+    /// iCalendar file
+    Events {
+        /// Calendar file (.ics)
+        calendar: PathBuf,
+        /// Routes web page
+        #[arg(long, default_value = insider::ROUTES_URL)]
+        routes_page: String,
+        /// Running routes web page
+        #[arg(long, default_value = insider::RUNNING_ROUTES_URL)]
+        running_page: String,
+        /// Units for distances and elevations: metric or imperial
+        #[arg(long, default_value = "metric")]
+        units: UnitSystem,
+    },
+    /// Export a route's elevation profile as a GPX track or TCX course
+    Export {
+        /// Route name
+        route: String,
+        /// File format: gpx or tcx
+        #[arg(long, default_value = "gpx")]
+        format: ExportFormat,
+        /// File to write. Prints to stdout by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Routes web page
+        #[arg(long, default_value = insider::ROUTES_URL)]
+        routes_page: String,
+        /// Rider power in W/kg, used for TCX times
+        #[arg(long, default_value_t = 3.0)]
+        wkg: f64,
+        /// Rider weight in kg, used for TCX times
+        #[arg(long, default_value_t = 75.0)]
+        weight: f64,
+    },
+    /// Print the duration and training load of each .zwo workout in a
+    /// directory, highest TSS first
+    Workouts {
+        /// Directory of .zwo files
+        dir: PathBuf,
+        /// Rider FTP in W
+        #[arg(long, default_value_t = 250.0)]
+        ftp: f64,
+    },
+    /// Search the local workout catalogue, downloading Zwift Insider's
+    /// workouts into it first if asked
+    Catalogue {
+        /// Catalogue directory
+        #[arg(long, default_value = "workouts")]
+        dir: PathBuf,
+        /// Download new workouts from the workouts page before searching
+        #[arg(long)]
+        update: bool,
+        /// Workouts web page
+        #[arg(long, default_value = insider::WORKOUTS_URL)]
+        workouts_page: String,
+        /// Shortest workout in minutes
+        #[arg(long, default_value_t = 0)]
+        min_minutes: u64,
+        /// Longest workout in minutes
+        #[arg(long, default_value_t = 600)]
+        max_minutes: u64,
+        /// Only list workouts with this focus or tag, e.g. threshold
+        #[arg(long)]
+        focus: Option<String>,
+    },
+    /// Explain which elements of a web page a selector does or does not match
+    Explain {
+        /// Web page to query
+        web_page: String,
+        /// Selector to explain
+        selector: String,
+        /// Only print elements that matched
+        #[arg(long)]
+        matched_only: bool,
+    },
+}
 
-    let args = Args::parse();
+/// Quotes a CSV field if it contains a separator or quote.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Downloads the cycling routes page, and the running routes page unless only
+/// cycling routes are wanted.
+async fn download_routes(
+    web_page: &str,
+    running_page: &str,
+    sport: Option<Sport>,
+) -> Result<Vec<Route>> {
+    let web_page = download_webpage(web_page).await?;
+    let mut routes = insider::parse_routes(&Dom::parse(&web_page)?).await?;
+    if sport != Some(Sport::Cycling) {
+        let running_page = download_webpage(running_page).await?;
+        let running_routes = insider::parse_running_routes(&Dom::parse(&running_page)?).await?;
+        insider::merge_routes(&mut routes, running_routes);
+    }
+    if let Some(sport) = sport {
+        routes.retain(|route| route.sport == sport);
+    }
+    Ok(routes)
+}
 
-    let web_page = download_webpage(&args.web_page).await?;
+async fn print_routes(
+    web_page: &str,
+    running_page: &str,
+    sport: Option<Sport>,
+    units: UnitSystem,
+) -> Result<()> {
+    let routes = download_routes(web_page, running_page, sport).await?;
+
+    println!(
+        "Route,World,Distance,Elevation,Lead-In,Lead-In Elevation,Badge XP,Sport,Restrictions,URL"
+    );
+    for route in routes {
+        let fields = [
+            csv_field(&route.name),
+            route.world_name().to_string(),
+            route.distance.format(units),
+            route.elevation.format(units),
+            route.lead_in_distance.format(units),
+            route.lead_in_elevation.format(units),
+            route.badge_xp.map(|xp| xp.to_string()).unwrap_or_default(),
+            route.sport.to_string(),
+            csv_field(
+                &route
+                    .restrictions
+                    .iter()
+                    .map(|restriction| restriction.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            csv_field(route.detail_url.as_deref().unwrap_or_default()),
+        ];
+        println!("{}", fields.join(","));
+    }
+
+    Ok(())
+}
+
+/// Formats a duration as hours and minutes, e.g. `1:05`.
+fn format_duration(duration: Duration) -> String {
+    let minutes = (duration.as_secs_f64() / 60.0).round() as u64;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+async fn print_recommendations(
+    web_page: &str,
+    duration: RangeInclusive<Duration>,
+    rider: &Rider,
+    completed: Option<&Path>,
+    units: UnitSystem,
+) -> Result<()> {
+    let completed = match completed {
+        Some(path) => recommend::load_completed_routes(path)?,
+        None => Vec::new(),
+    };
+    let web_page = download_webpage(web_page).await?;
     let dom = Dom::parse(&web_page)?;
+    let routes = insider::parse_routes(&dom).await?;
 
-    let tables = html_query::select(&dom, "table").await?;
-    for table in &tables {
-        let rows = html_query::find(table, "tr").await?;
-        let mut first_row = true;
-        for row in &rows {
-            let cells = html_query::find(row, if first_row { "th" } else { "td" }).await?;
-            for cell in &cells {
-                let mut text_found = false;
-                for child in &cell.children {
-                    if let Node::Text(text) = child {
-                        print!("{text},");
-                        text_found = true;
-                        break;
-                    } else if let Node::Element(element) = child {
-                        if element.name == "a" {
-                            for child in &element.children {
-                                if let Node::Text(text) = child {
-                                    print!("{text},");
-                                    text_found = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-                if !text_found {
-                    for child in &cell.children {
-                        print!("{:?}", child);
-                    }
-                }
+    println!("Route,World,Distance,Elevation,Badge XP,Estimated Time");
+    for recommendation in recommend::recommend(&routes, &completed, rider, duration) {
+        let route = recommendation.route;
+        let fields = [
+            csv_field(&route.name),
+            route.world_name().to_string(),
+            (route.distance + route.lead_in_distance).format(units),
+            (route.elevation + route.lead_in_elevation).format(units),
+            route.badge_xp.map(|xp| xp.to_string()).unwrap_or_default(),
+            format_duration(recommendation.estimated_time),
+        ];
+        println!("{}", fields.join(","));
+    }
+
+    Ok(())
+}
+
+async fn print_workout_routes(
+    routes_page: &str,
+    duration: Duration,
+    rider: &Rider,
+    tolerance: Duration,
+    units: UnitSystem,
+) -> Result<()> {
+    let routes_page = download_webpage(routes_page).await?;
+    let routes = insider::parse_routes(&Dom::parse(&routes_page)?).await?;
+
+    println!("Route,World,Distance,Elevation,Badge XP,Estimated Time");
+    for recommendation in recommend::match_workout(&routes, rider, duration, tolerance) {
+        let route = recommendation.route;
+        let fields = [
+            csv_field(&route.name),
+            route.world_name().to_string(),
+            (route.distance + route.lead_in_distance).format(units),
+            (route.elevation + route.lead_in_elevation).format(units),
+            route.badge_xp.map(|xp| xp.to_string()).unwrap_or_default(),
+            format_duration(recommendation.estimated_time),
+        ];
+        println!("{}", fields.join(","));
+    }
+
+    Ok(())
+}
+
+async fn print_level_plan(
+    routes_page: &str,
+    current_xp: u32,
+    target_level: u32,
+    weekly_time: Duration,
+    rider: &Rider,
+    completed: Option<&Path>,
+    units: UnitSystem,
+) -> Result<()> {
+    let completed = match completed {
+        Some(path) => recommend::load_completed_routes(path)?,
+        None => Vec::new(),
+    };
+    let routes_page = download_webpage(routes_page).await?;
+    let routes = insider::parse_routes(&Dom::parse(&routes_page)?).await?;
+    let plan = progression::plan_level(
+        &Progression::default(),
+        &routes,
+        &completed,
+        rider,
+        current_xp,
+        target_level,
+        weekly_time,
+    )?;
+
+    println!("Week,Ride,World,Distance,Time,XP,Level");
+    for step in &plan.steps {
+        let (ride, world, distance) = match &step.activity {
+            PlanActivity::Route(route) => (
+                route.name.clone(),
+                route.world_name().to_string(),
+                route.distance + route.lead_in_distance,
+            ),
+            PlanActivity::Distance(distance) => {
+                ("Any flat route".to_string(), String::new(), *distance)
             }
-            println!();
-            first_row = false;
+        };
+        let fields = [
+            step.week.to_string(),
+            csv_field(&ride),
+            world,
+            distance.format(units),
+            format_duration(step.time),
+            step.xp.to_string(),
+            step.level.to_string(),
+        ];
+        println!("{}", fields.join(","));
+    }
+    println!(
+        "Level {target_level} in {} weeks, {} of riding",
+        plan.weeks,
+        format_duration(plan.total_time)
+    );
+
+    Ok(())
+}
+
+async fn print_rideable_routes(
+    date: NaiveDate,
+    routes_page: &str,
+    calendar_page: &str,
+) -> Result<()> {
+    let routes_page = download_webpage(routes_page).await?;
+    let routes = insider::parse_routes(&Dom::parse(&routes_page)?).await?;
+    let calendar_page = download_webpage(calendar_page).await?;
+    let schedule = insider::parse_world_schedule(&Dom::parse(&calendar_page)?).await?;
+    if schedule.guest_worlds(date).is_none() {
+        warn!("The guest world calendar doesn't cover {date}, only Watopia is listed");
+    }
+
+    println!("Route,World");
+    for route in schedule.rideable_routes(&routes, date) {
+        println!("{},{}", csv_field(&route.name), route.world_name());
+    }
+
+    Ok(())
+}
+
+async fn save_snapshot(dir: &Path, web_page: &str, running_page: &str) -> Result<()> {
+    let routes = download_routes(web_page, running_page, None).await?;
+    let path = SnapshotStore::new(dir).save(&RouteSnapshot::new(routes))?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+fn print_diff(old: &Path, new: &Path, json: bool) -> Result<()> {
+    let old = RouteSnapshot::load(old)?;
+    let new = RouteSnapshot::load(new)?;
+    let diff = diff::diff_routes(&old.routes, &new.routes);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{diff}");
+    }
+    Ok(())
+}
+
+async fn print_events(
+    calendar: &Path,
+    routes_page: &str,
+    running_page: &str,
+    units: UnitSystem,
+) -> Result<()> {
+    let events = events::load_calendar(calendar)?;
+    let routes = download_routes(routes_page, running_page, None).await?;
+
+    println!("Start,Event,Route,World,Laps,Distance,Elevation,Badge XP");
+    for report in events::report_events(&events, &routes) {
+        let event = report.event;
+        if report.route.is_none() {
+            warn!("No route found for \"{}\"", event.summary);
+        }
+        let fields = [
+            event
+                .start
+                .as_ref()
+                .map(|start| start.to_string())
+                .unwrap_or_default(),
+            csv_field(&event.summary),
+            report
+                .route
+                .map(|route| csv_field(&route.name))
+                .unwrap_or_default(),
+            report
+                .route
+                .map(|route| route.world_name().to_string())
+                .unwrap_or_default(),
+            report.laps.to_string(),
+            report
+                .distance()
+                .map(|distance| distance.format(units))
+                .unwrap_or_default(),
+            report
+                .elevation()
+                .map(|elevation| elevation.format(units))
+                .unwrap_or_default(),
+            report
+                .badge_xp()
+                .map(|xp| xp.to_string())
+                .unwrap_or_default(),
+        ];
+        println!("{}", fields.join(","));
+    }
+
+    Ok(())
+}
+
+async fn export_route(
+    name: &str,
+    format: ExportFormat,
+    output: Option<&Path>,
+    routes_page: &str,
+    rider: &Rider,
+) -> Result<()> {
+    let routes_page = download_webpage(routes_page).await?;
+    let routes = insider::parse_routes(&Dom::parse(&routes_page)?).await?;
+    let route = insider::find_route(&routes, name).ok_or_else(|| anyhow!("No route \"{name}\""))?;
+    let world = route
+        .world
+        .ok_or_else(|| anyhow!("The world of \"{}\" is unknown", route.name))?;
+    let (_, details) = insider::fetch_route_details(std::slice::from_ref(route))
+        .await
+        .pop()
+        .ok_or_else(|| anyhow!("\"{}\" has no detail page", route.name))?;
+    let profile = details?
+        .profile
+        .ok_or_else(|| anyhow!("No elevation profile for \"{}\"", route.name))?;
+
+    let exported = match format {
+        ExportFormat::Gpx => export::to_gpx(&route.name, world, &profile),
+        ExportFormat::Tcx => export::to_tcx(&route.name, world, &profile, rider, Utc::now()),
+    };
+    match output {
+        Some(path) => std::fs::write(path, exported)?,
+        None => print!("{exported}"),
+    }
+    Ok(())
+}
+
+fn print_workout_metrics(dir: &Path, ftp: f64) -> Result<()> {
+    let mut rows = Vec::new();
+    for (path, workout) in workout::load_workouts(dir)? {
+        match workout {
+            Ok(workout) => {
+                let metrics = workout.metrics(ftp);
+                rows.push((path, workout, metrics));
+            }
+            Err(error) => warn!("Skipping {}: {error:#}", path.display()),
+        }
+    }
+    rows.sort_by(|(_, _, a), (_, _, b)| b.tss.total_cmp(&a.tss));
+
+    let zones: Vec<_> = Zone::ALL
+        .iter()
+        .map(|zone| format!("{zone} Minutes"))
+        .collect();
+    println!("File,Workout,Duration,NP,IF,TSS,{}", zones.join(","));
+    for (path, workout, metrics) in rows {
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut fields = vec![
+            csv_field(&file),
+            csv_field(&workout.name),
+            format_duration(metrics.duration),
+            format!("{:.0}", metrics.normalized_power),
+            format!("{:.2}", metrics.intensity_factor),
+            format!("{:.0}", metrics.tss),
+        ];
+        fields.extend(
+            Zone::ALL
+                .iter()
+                .map(|zone| format!("{:.1}", metrics.time_in_zone(*zone).as_secs_f64() / 60.0)),
+        );
+        println!("{}", fields.join(","));
+    }
+    Ok(())
+}
+
+async fn search_catalogue(
+    dir: &Path,
+    update: Option<&str>,
+    duration: RangeInclusive<Duration>,
+    focus: Option<&str>,
+) -> Result<()> {
+    let mut catalogue = WorkoutCatalogue::open(dir)?;
+    if let Some(workouts_page) = update {
+        let added = catalogue.update(workouts_page).await?;
+        catalogue.save()?;
+        info!("Added {added} workouts to {}", dir.display());
+    }
+
+    println!("Workout,Collection,Duration,Focus,File");
+    for entry in catalogue.search(duration, focus) {
+        let fields = [
+            csv_field(&entry.listing.name),
+            csv_field(&entry.listing.collection),
+            format_duration(entry.workout_duration),
+            csv_field(&entry.listing.focus.join(", ")),
+            csv_field(&catalogue.path(entry).display().to_string()),
+        ];
+        println!("{}", fields.join(","));
+    }
+    Ok(())
+}
+
+async fn explain_selector(web_page: &str, selector: &str, matched_only: bool) -> Result<()> {
+    let web_page = download_webpage(web_page).await?;
+    let dom = Dom::parse(&web_page)?;
+
+    for explanation in html_query::explain(&dom, selector).await? {
+        if !matched_only || explanation.matched() {
+            print!("{explanation}");
         }
     }
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Log at debug level unless RUST_LOG says otherwise
+    Builder::new()
+        .filter_level(LevelFilter::Debug)
+        .parse_default_env()
+        .format_timestamp_micros()
+        .init();
+
+    let args = Args::parse();
+
+    match args.command {
+        Command::Routes {
+            web_page,
+            running_page,
+            sport,
+            units,
+        } => print_routes(&web_page, &running_page, sport, units).await,
+        Command::Recommend {
+            web_page,
+            min_minutes,
+            max_minutes,
+            wkg,
+            weight,
+            completed,
+            units,
+        } => {
+            let rider = Rider {
+                weight_kg: weight,
+                watts_per_kg: wkg,
+                ..Rider::default()
+            };
+            let duration =
+                Duration::from_secs(min_minutes * 60)..=Duration::from_secs(max_minutes * 60);
+            print_recommendations(&web_page, duration, &rider, completed.as_deref(), units).await
+        }
+        Command::FitWorkout {
+            minutes,
+            zwo,
+            wkg,
+            ftp,
+            weight,
+            tolerance_minutes,
+            routes_page,
+            units,
+        } => {
+            let rider = Rider {
+                weight_kg: weight,
+                watts_per_kg: wkg,
+                ..Rider::default()
+            };
+            let (duration, rider) = match (zwo, minutes) {
+                (Some(path), _) => {
+                    let workout = Workout::load(path)?;
+                    let rider = recommend::workout_rider(&workout, ftp, &rider);
+                    (workout.duration(), rider)
+                }
+                (None, minutes) => (Duration::from_secs(minutes.unwrap_or_default() * 60), rider),
+            };
+            let tolerance = Duration::from_secs(tolerance_minutes * 60);
+            print_workout_routes(&routes_page, duration, &rider, tolerance, units).await
+        }
+        Command::PlanLevel {
+            xp,
+            target_level,
+            weekly_hours,
+            wkg,
+            weight,
+            completed,
+            routes_page,
+            units,
+        } => {
+            let rider = Rider {
+                weight_kg: weight,
+                watts_per_kg: wkg,
+                ..Rider::default()
+            };
+            let weekly_time = Duration::from_secs_f64(weekly_hours.max(0.0) * 3600.0);
+            print_level_plan(
+                &routes_page,
+                xp,
+                target_level,
+                weekly_time,
+                &rider,
+                completed.as_deref(),
+                units,
+            )
+            .await
+        }
+        Command::Rideable {
+            date,
+            routes_page,
+            calendar_page,
+        } => {
+            let date = date.unwrap_or_else(|| Local::now().date_naive());
+            print_rideable_routes(date, &routes_page, &calendar_page).await
+        }
+        Command::Snapshot {
+            dir,
+            web_page,
+            running_page,
+        } => save_snapshot(&dir, &web_page, &running_page).await,
+        Command::Diff { old, new, json } => print_diff(&old, &new, json),
+        Command::Events {
+            calendar,
+            routes_page,
+            running_page,
+            units,
+        } => print_events(&calendar, &routes_page, &running_page, units).await,
+        Command::Export {
+            route,
+            format,
+            output,
+            routes_page,
+            wkg,
+            weight,
+        } => {
+            let rider = Rider {
+                weight_kg: weight,
+                watts_per_kg: wkg,
+                ..Rider::default()
+            };
+            export_route(&route, format, output.as_deref(), &routes_page, &rider).await
+        }
+        Command::Workouts { dir, ftp } => print_workout_metrics(&dir, ftp),
+        Command::Catalogue {
+            dir,
+            update,
+            workouts_page,
+            min_minutes,
+            max_minutes,
+            focus,
+        } => {
+            let duration =
+                Duration::from_secs(min_minutes * 60)..=Duration::from_secs(max_minutes * 60);
+            let update = update.then_some(workouts_page.as_str());
+            search_catalogue(&dir, update, duration, focus.as_deref()).await
+        }
+        Command::Explain {
+            web_page,
+            selector,
+            matched_only,
+        } => explain_selector(&web_page, &selector, matched_only).await,
+    }
+}
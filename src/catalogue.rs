@@ -0,0 +1,262 @@
+//! A local catalogue of Zwift Insider workouts: the downloaded `.zwo` files
+//! and an index for searching them.
+
+use std::{fs, ops::RangeInclusive, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use html_parser::Dom;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    insider::{self, download_webpage, WorkoutListing},
+    workout::{self, Workout},
+};
+
+const INDEX_FILE: &str = "index.json";
+
+/// A cached workout: the listing it was found in and what its file says.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogueEntry {
+    pub listing: WorkoutListing,
+    /// The `.zwo` file, relative to the catalogue directory
+    pub file: String,
+    /// The duration of the workout's blocks, see [`Workout::duration`]
+    pub workout_duration: Duration,
+    pub tags: Vec<String>,
+}
+
+impl CatalogueEntry {
+    /// Whether the workout's focus or tags mention `focus`, ignoring case.
+    pub fn has_focus(&self, focus: &str) -> bool {
+        let focus = focus.to_lowercase();
+        self.listing
+            .focus
+            .iter()
+            .chain(&self.tags)
+            .any(|existing| existing.to_lowercase().contains(&focus))
+    }
+}
+
+/// A directory of downloaded `.zwo` files with an index of them.
+#[derive(Debug, Clone)]
+pub struct WorkoutCatalogue {
+    dir: PathBuf,
+    entries: Vec<CatalogueEntry>,
+}
+
+/// The cache file name for a listing, from its collection and the name of
+/// the linked file, e.g. `threshold-builders-over-unders.zwo`.
+fn file_name(listing: &WorkoutListing) -> String {
+    let file = listing
+        .zwo_url
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .replace("%20", " ");
+    let stem = file.strip_suffix(".zwo").unwrap_or(&file);
+    let slug = format!("{} {stem}", listing.collection)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("{slug}.zwo")
+}
+
+impl WorkoutCatalogue {
+    /// Opens the catalogue in `dir`, which is empty if it hasn't been saved.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let index = dir.join(INDEX_FILE);
+        let entries = if index.exists() {
+            let json = fs::read_to_string(&index)
+                .with_context(|| format!("Failed to read {}", index.display()))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("Invalid catalogue index {}", index.display()))?
+        } else {
+            Vec::new()
+        };
+        Ok(WorkoutCatalogue { dir, entries })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let index = self.dir.join(INDEX_FILE);
+        fs::write(&index, serde_json::to_string_pretty(&self.entries)?)
+            .with_context(|| format!("Failed to write {}", index.display()))
+    }
+
+    pub fn entries(&self) -> &[CatalogueEntry] {
+        &self.entries
+    }
+
+    pub fn path(&self, entry: &CatalogueEntry) -> PathBuf {
+        self.dir.join(&entry.file)
+    }
+
+    pub fn workout(&self, entry: &CatalogueEntry) -> Result<Workout> {
+        Workout::load(self.path(entry))
+    }
+
+    /// Adds a workout to the catalogue, downloading its `.zwo` file unless it
+    /// is already cached. The listing's link must be absolute: resolve links
+    /// against the collection page they were found on first, as
+    /// [`WorkoutCatalogue::update`] does.
+    pub async fn add(&mut self, listing: WorkoutListing) -> Result<&CatalogueEntry> {
+        let url = reqwest::Url::parse(&listing.zwo_url).with_context(|| {
+            format!(
+                "Workout \"{}\" links to \"{}\", which isn't an absolute URL",
+                listing.name, listing.zwo_url
+            )
+        })?;
+        let file = file_name(&listing);
+        let path = self.dir.join(&file);
+        let workout = if path.exists() {
+            debug!("Using cached {}", path.display());
+            Workout::load(&path)?
+        } else {
+            let zwo = download_webpage(url.as_str()).await?;
+            let workout =
+                workout::parse_zwo(&zwo).with_context(|| format!("Invalid workout {url}"))?;
+            fs::create_dir_all(&self.dir)
+                .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+            fs::write(&path, zwo).with_context(|| format!("Failed to write {}", path.display()))?;
+            workout
+        };
+
+        let entry = CatalogueEntry {
+            listing,
+            file,
+            workout_duration: workout.duration(),
+            tags: workout.tags,
+        };
+        self.entries.retain(|existing| existing.file != entry.file);
+        self.entries.push(entry);
+        Ok(&self.entries[self.entries.len() - 1])
+    }
+
+    /// Downloads the workouts page and every collection it links to, adding
+    /// their workouts. Pages and files that fail are logged and skipped.
+    /// Returns the number of workouts added.
+    pub async fn update(&mut self, workouts_page: &str) -> Result<usize> {
+        let base = reqwest::Url::parse(workouts_page)?;
+        let page = download_webpage(workouts_page).await?;
+        let collections = insider::parse_workout_collections(&Dom::parse(&page)?).await?;
+
+        let mut added = 0;
+        for collection in collections {
+            // Links to .zwo files are made absolute against the collection page
+            let listings = async {
+                let url = base.join(&collection.url)?;
+                let page = download_webpage(url.as_str()).await?;
+                let mut listings = insider::parse_workout_collection(&Dom::parse(&page)?).await?;
+                for listing in &mut listings {
+                    listing.zwo_url = url.join(&listing.zwo_url)?.to_string();
+                }
+                anyhow::Ok(listings)
+            }
+            .await;
+            let listings = match listings {
+                Ok(listings) => listings,
+                Err(error) => {
+                    warn!("Skipping collection \"{}\": {error:#}", collection.name);
+                    continue;
+                }
+            };
+            for listing in listings {
+                let name = listing.name.clone();
+                match self.add(listing).await {
+                    Ok(_) => added += 1,
+                    Err(error) => warn!("Skipping workout \"{name}\": {error:#}"),
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// The workouts lasting within `duration` with `focus`, if given, shortest
+    /// first.
+    pub fn search(
+        &self,
+        duration: RangeInclusive<Duration>,
+        focus: Option<&str>,
+    ) -> Vec<&CatalogueEntry> {
+        let mut found: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| duration.contains(&entry.workout_duration))
+            .filter(|entry| focus.is_none_or(|focus| entry.has_focus(focus)))
+            .collect();
+        found.sort_by_key(|entry| entry.workout_duration);
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use html_parser::Dom;
+
+    use super::{file_name, WorkoutCatalogue};
+    use crate::insider::parse_workout_collection;
+
+    static COLLECTION_HTML: &str = include_str!("../fixtures/insider/workout-collection.html");
+    static OVER_UNDERS_ZWO: &str = include_str!("../fixtures/workouts/over-unders.zwo");
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[tokio::test]
+    async fn test_catalogue() {
+        let listings = parse_workout_collection(&Dom::parse(COLLECTION_HTML).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            file_name(&listings[0]),
+            "threshold-builders-over-unders.zwo"
+        );
+        assert_eq!(
+            file_name(&listings[2]),
+            "threshold-builders-threshold-pyramid.zwo"
+        );
+
+        // Cached files are used instead of downloading
+        let dir = std::env::temp_dir().join(format!("catalogue-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(file_name(&listings[0])), OVER_UNDERS_ZWO).unwrap();
+        let mut catalogue = WorkoutCatalogue::open(&dir).unwrap();
+        assert!(catalogue.entries().is_empty());
+        let entry = catalogue.add(listings[0].clone()).await.unwrap();
+        assert_eq!(entry.workout_duration, Duration::from_secs(3020));
+        assert_eq!(entry.tags, ["THRESHOLD", "INTERVALS"]);
+        // Relative links can't be resolved without the collection page
+        assert!(catalogue.add(listings[1].clone()).await.is_err());
+        catalogue.add(listings[0].clone()).await.unwrap();
+        assert_eq!(catalogue.entries().len(), 1);
+        catalogue.save().unwrap();
+
+        let reopened = WorkoutCatalogue::open(&dir).unwrap();
+        let workout = reopened.workout(&reopened.entries()[0]);
+        let searches = [
+            reopened.search(minutes(45)..=minutes(60), None).len(),
+            reopened
+                .search(minutes(45)..=minutes(60), Some("sprint"))
+                .len(),
+            reopened
+                .search(minutes(45)..=minutes(60), Some("intervals"))
+                .len(),
+            reopened
+                .search(minutes(45)..=minutes(60), Some("recovery"))
+                .len(),
+            reopened.search(minutes(60)..=minutes(90), None).len(),
+        ];
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(reopened.entries(), catalogue.entries());
+        assert_eq!(workout.unwrap().name, "Over-Unders & Sprints");
+        assert_eq!(searches, [1, 1, 1, 0, 0]);
+    }
+}
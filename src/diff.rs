@@ -0,0 +1,215 @@
+//! Compares two versions of the routes table.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::insider::{Route, Sport};
+
+/// A field whose value differs between two versions of a route.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// A route present in both versions with at least one changed field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteChange {
+    pub name: String,
+    pub sport: Sport,
+    pub changes: Vec<FieldChange>,
+}
+
+/// The routes added, removed and changed between two versions of the routes
+/// table. Routes are matched by name and sport.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteDiff {
+    pub added: Vec<Route>,
+    pub removed: Vec<Route>,
+    pub changed: Vec<RouteChange>,
+}
+
+impl RouteDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn is_same_route(a: &Route, b: &Route) -> bool {
+    a.sport == b.sport && a.matches_name(&b.name)
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// The fields of `route` that are compared, formatted for display. Distances
+/// are given to 10 m so small corrections are reported.
+fn fields(route: &Route) -> [(&'static str, String); 9] {
+    [
+        ("name", route.name.clone()),
+        ("world", route.world_name().to_string()),
+        ("distance", format!("{:.2} km", route.distance.km())),
+        ("elevation", format!("{:.0} m", route.elevation.meters())),
+        (
+            "lead_in_distance",
+            format!("{:.2} km", route.lead_in_distance.km()),
+        ),
+        (
+            "lead_in_elevation",
+            format!("{:.0} m", route.lead_in_elevation.meters()),
+        ),
+        ("badge_xp", optional(route.badge_xp)),
+        (
+            "restrictions",
+            route
+                .restrictions
+                .iter()
+                .map(|restriction| restriction.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        ("detail_url", optional(route.detail_url.as_deref())),
+    ]
+}
+
+/// Compares the `old` and `new` versions of the routes table.
+pub fn diff_routes(old: &[Route], new: &[Route]) -> RouteDiff {
+    let mut diff = RouteDiff::default();
+    for route in new {
+        match old.iter().find(|old_route| is_same_route(old_route, route)) {
+            Some(old_route) => {
+                let changes: Vec<_> = fields(old_route)
+                    .into_iter()
+                    .zip(fields(route))
+                    .filter(|((_, old), (_, new))| old != new)
+                    .map(|((field, old), (_, new))| FieldChange {
+                        field: field.to_string(),
+                        old,
+                        new,
+                    })
+                    .collect();
+                if !changes.is_empty() {
+                    diff.changed.push(RouteChange {
+                        name: route.name.clone(),
+                        sport: route.sport,
+                        changes,
+                    });
+                }
+            }
+            None => diff.added.push(route.clone()),
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|old_route| !new.iter().any(|route| is_same_route(old_route, route)))
+        .cloned()
+        .collect();
+    diff
+}
+
+impl fmt::Display for RouteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for route in &self.added {
+            writeln!(
+                f,
+                "+ {} ({}, {}): {}, {}",
+                route.name,
+                route.world_name(),
+                route.sport,
+                route.distance,
+                route.elevation
+            )?;
+        }
+        for route in &self.removed {
+            writeln!(
+                f,
+                "- {} ({}, {})",
+                route.name,
+                route.world_name(),
+                route.sport
+            )?;
+        }
+        for route in &self.changed {
+            writeln!(f, "~ {} ({})", route.name, route.sport)?;
+            for change in &route.changes {
+                writeln!(
+                    f,
+                    "    {}: \"{}\" -> \"{}\"",
+                    change.field, change.old, change.new
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::Dom;
+
+    use super::{diff_routes, FieldChange};
+    use crate::{
+        insider::{parse_routes, RouteRestriction},
+        units::Distance,
+    };
+
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+
+    #[tokio::test]
+    async fn test_diff_routes() {
+        let old = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        assert!(diff_routes(&old, &old).is_empty());
+        assert_eq!(diff_routes(&old, &old).to_string(), "No changes\n");
+
+        let mut new = old.clone();
+        let removed = new.remove(2);
+        new[0].distance = Distance::from_km(17.4);
+        new[0]
+            .restrictions
+            .insert(RouteRestriction::MinimumLevel(3));
+        let mut added = old[1].clone();
+        added.name = "Road to Sky Reverse".to_string();
+        new.push(added.clone());
+
+        let diff = diff_routes(&old, &new);
+        assert_eq!(diff.added, [added]);
+        assert_eq!(diff.removed, [removed]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].name, "Tempus Fugit");
+        assert_eq!(
+            diff.changed[0].changes,
+            [
+                FieldChange {
+                    field: "distance".to_string(),
+                    old: "17.30 km".to_string(),
+                    new: "17.40 km".to_string(),
+                },
+                FieldChange {
+                    field: "restrictions".to_string(),
+                    old: "".to_string(),
+                    new: "Level 3+".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "+ Road to Sky Reverse (Watopia, Cycling): 17.5 km, 1036 m\n\
+             - Greatest London Flat (London, Cycling)\n\
+             ~ Tempus Fugit (Cycling)\n    \
+             distance: \"17.30 km\" -> \"17.40 km\"\n    \
+             restrictions: \"\" -> \"Level 3+\"\n"
+        );
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(json["changed"][0]["changes"][0]["field"], "distance");
+        assert_eq!(json["removed"][0]["name"], "Greatest London Flat");
+    }
+}
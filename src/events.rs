@@ -0,0 +1,457 @@
+//! Club event calendars (`.ics` files) and the routes their events ride.
+
+use std::{fmt, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    insider::{self, normalize_name, Route},
+    units::{Distance, Elevation},
+};
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// When an event starts, as given by its `DTSTART`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventStart {
+    Utc(DateTime<Utc>),
+    /// A local time, in the named time zone if one is given
+    Local {
+        time: NaiveDateTime,
+        time_zone: Option<String>,
+    },
+    /// An all day event
+    Date(NaiveDate),
+}
+
+impl fmt::Display for EventStart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventStart::Utc(time) => write!(f, "{} UTC", time.format("%Y-%m-%d %H:%M")),
+            EventStart::Local { time, time_zone } => {
+                write!(f, "{}", time.format("%Y-%m-%d %H:%M"))?;
+                match time_zone {
+                    Some(time_zone) => write!(f, " {time_zone}"),
+                    None => Ok(()),
+                }
+            }
+            EventStart::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+/// A `VEVENT` of a calendar.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub url: Option<String>,
+    pub start: Option<EventStart>,
+}
+
+/// A content line, `NAME;PARAM=VALUE:value`.
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Splits `text` at each `separator` outside of double quotes.
+fn split_unquoted(text: &str, separator: char) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    text.split(move |c: char| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == separator && !quoted
+    })
+}
+
+fn parse_property(line: &str) -> Result<Property<'_>> {
+    let mut quoted = false;
+    let colon = line
+        .find(|c: char| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ':' && !quoted
+        })
+        .ok_or_else(|| anyhow!("Invalid calendar line \"{line}\""))?;
+    let mut head = split_unquoted(&line[..colon], ';');
+    let name = head.next().unwrap_or_default().trim().to_uppercase();
+    let params = head
+        .filter_map(|param| param.split_once('='))
+        .map(|(param, value)| (param.trim().to_uppercase(), value.trim_matches('"')))
+        .collect();
+    Ok(Property {
+        name,
+        params,
+        value: &line[colon + 1..],
+    })
+}
+
+/// Joins folded lines, those starting with a space or tab, to the line they
+/// continue.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Undoes the escaping of `TEXT` values: `\n`, `\,`, `\;` and `\\`.
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+fn parse_start(property: &Property) -> Result<EventStart> {
+    let value = property.value.trim();
+    let is_date = property
+        .param("VALUE")
+        .is_some_and(|value| value.eq_ignore_ascii_case("DATE"));
+    let start = if is_date || !value.contains('T') {
+        EventStart::Date(NaiveDate::parse_from_str(value, "%Y%m%d")?)
+    } else if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        EventStart::Utc(NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT)?.and_utc())
+    } else {
+        EventStart::Local {
+            time: NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)?,
+            time_zone: property.param("TZID").map(str::to_string),
+        }
+    };
+    Ok(start)
+}
+
+impl Event {
+    fn set(&mut self, property: &Property) -> Result<()> {
+        match property.name.as_str() {
+            "UID" => self.uid = Some(unescape(property.value)),
+            "SUMMARY" => self.summary = unescape(property.value),
+            "DESCRIPTION" => self.description = Some(unescape(property.value)),
+            "LOCATION" => self.location = Some(unescape(property.value)),
+            "URL" => self.url = Some(property.value.to_string()),
+            "DTSTART" => {
+                self.start = Some(
+                    parse_start(property)
+                        .with_context(|| format!("Invalid DTSTART \"{}\"", property.value))?,
+                )
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The route the event rides: the route named on a `Route:` line of the
+    /// description, or else the longest route name mentioned in the summary,
+    /// location or description, in that order. Names are compared as in
+    /// [`Route::matches_name`].
+    pub fn find_route<'a>(&self, routes: &'a [Route]) -> Option<&'a Route> {
+        let labelled = self
+            .description
+            .iter()
+            .flat_map(|description| description.lines())
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(label, _)| label.trim().eq_ignore_ascii_case("route"))
+            .find_map(|(_, name)| insider::find_route(routes, name));
+        labelled.or_else(|| {
+            [
+                Some(&self.summary),
+                self.location.as_ref(),
+                self.description.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .find_map(|text| mentioned_route(routes, text))
+        })
+    }
+
+    /// The number of laps, from text such as "2 laps" in the summary or
+    /// description, or 1.
+    pub fn laps(&self) -> u32 {
+        [Some(&self.summary), self.description.as_ref()]
+            .into_iter()
+            .flatten()
+            .find_map(|text| {
+                let text = normalize_name(text);
+                let words: Vec<_> = text.split(' ').collect();
+                words
+                    .windows(2)
+                    .filter(|pair| pair[1] == "lap" || pair[1] == "laps")
+                    .find_map(|pair| pair[0].parse().ok())
+            })
+            .filter(|laps| *laps > 0)
+            .unwrap_or(1)
+    }
+}
+
+/// The route with the longest name found, as whole words, in `text`.
+fn mentioned_route<'a>(routes: &'a [Route], text: &str) -> Option<&'a Route> {
+    let text = format!(" {} ", normalize_name(text));
+    routes
+        .iter()
+        .map(|route| (route, normalize_name(&route.name)))
+        .filter(|(_, name)| !name.is_empty() && text.contains(&format!(" {name} ")))
+        .max_by_key(|(_, name)| name.len())
+        .map(|(route, _)| route)
+}
+
+/// Parses the events of an iCalendar file. Components other than events,
+/// such as time zones and alarms, are skipped.
+pub fn parse_ics(text: &str) -> Result<Vec<Event>> {
+    let mut components: Vec<String> = Vec::new();
+    let mut event = None;
+    let mut events = Vec::new();
+    let mut found_calendar = false;
+    for line in unfold(text) {
+        let property = parse_property(&line)?;
+        match property.name.as_str() {
+            "BEGIN" => {
+                let component = property.value.trim().to_uppercase();
+                found_calendar |= component == "VCALENDAR";
+                if component == "VEVENT" {
+                    event = Some(Event::default());
+                }
+                components.push(component);
+            }
+            "END" => {
+                let component = property.value.trim().to_uppercase();
+                if components.pop().as_ref() != Some(&component) {
+                    return Err(anyhow!("Unexpected END:{component}"));
+                }
+                if component == "VEVENT" {
+                    events.extend(event.take());
+                }
+            }
+            _ if components
+                .last()
+                .is_some_and(|component| component == "VEVENT") =>
+            {
+                if let Some(event) = &mut event {
+                    event
+                        .set(&property)
+                        .with_context(|| format!("Invalid event \"{}\"", event.summary))?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !found_calendar {
+        Err(anyhow!("No VCALENDAR found"))
+    } else if let Some(component) = components.last() {
+        Err(anyhow!("{component} is missing its END"))
+    } else {
+        Ok(events)
+    }
+}
+
+/// Reads the events of an iCalendar file.
+pub fn load_calendar(path: impl AsRef<Path>) -> Result<Vec<Event>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read calendar {}", path.display()))?;
+    parse_ics(&text).with_context(|| format!("Invalid calendar {}", path.display()))
+}
+
+/// An event and the route it rides, if found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventReport<'a> {
+    pub event: &'a Event,
+    pub route: Option<&'a Route>,
+    pub laps: u32,
+}
+
+impl EventReport<'_> {
+    /// The distance of the lead-in and every lap of the route.
+    pub fn distance(&self) -> Option<Distance> {
+        self.route.map(|route| {
+            route.lead_in_distance
+                + Distance::from_meters(route.distance.meters() * f64::from(self.laps))
+        })
+    }
+
+    /// The elevation gain of the lead-in and every lap of the route.
+    pub fn elevation(&self) -> Option<Elevation> {
+        self.route.map(|route| {
+            route.lead_in_elevation
+                + Elevation::from_meters(route.elevation.meters() * f64::from(self.laps))
+        })
+    }
+
+    /// The XP for the route's badge, earned once however many laps are
+    /// ridden.
+    pub fn badge_xp(&self) -> Option<u32> {
+        self.route.and_then(|route| route.badge_xp)
+    }
+}
+
+/// Finds the route of each event, see [`Event::find_route`].
+pub fn report_events<'a>(events: &'a [Event], routes: &'a [Route]) -> Vec<EventReport<'a>> {
+    events
+        .iter()
+        .map(|event| EventReport {
+            event,
+            route: event.find_route(routes),
+            laps: event.laps(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use html_parser::Dom;
+
+    use super::{parse_ics, report_events, unescape, EventStart};
+    use crate::insider::parse_routes;
+
+    static CLUB_RIDES_ICS: &str = include_str!("../fixtures/events/club-rides.ics");
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"a\, b\; c\\d\nnext\N"), "a, b; c\\d\nnext\n");
+    }
+
+    #[test]
+    fn test_parse_ics() {
+        let events = parse_ics(CLUB_RIDES_ICS).unwrap();
+        assert_eq!(events.len(), 4);
+
+        let social = &events[0];
+        assert_eq!(
+            social.uid.as_deref(),
+            Some("2026-10-20-social@hilltopwheelers.example")
+        );
+        assert_eq!(social.summary, "Tuesday Social: Tempus Fugit");
+        assert_eq!(
+            social.description.as_deref(),
+            Some(
+                "No drop social ride at 2.0 W/kg, regroup at the end of each lap.\n\
+                 2 laps of the route."
+            )
+        );
+        assert_eq!(social.location.as_deref(), Some("Watopia"));
+        assert_eq!(
+            social.url.as_deref(),
+            Some("https://hilltopwheelers.example/events/tuesday-social")
+        );
+        assert_eq!(
+            social.start,
+            Some(EventStart::Utc(
+                Utc.with_ymd_and_hms(2026, 10, 20, 18, 0, 0).unwrap()
+            ))
+        );
+
+        // The alarm's description isn't the event's
+        let climb = &events[1];
+        assert_eq!(
+            climb.description.as_deref(),
+            Some("Steady pace up the Radio Tower.\nRoute: road-to-sky")
+        );
+        assert_eq!(
+            climb.start.as_ref().unwrap().to_string(),
+            "2026-10-22 19:00 Europe/London"
+        );
+
+        assert_eq!(
+            events[2].start,
+            Some(EventStart::Date(
+                NaiveDate::from_ymd_opt(2026, 10, 25).unwrap()
+            ))
+        );
+        assert_eq!(
+            events[3].start.as_ref().unwrap().to_string(),
+            "2026-10-27 18:30"
+        );
+
+        // Line endings may be CRLF
+        let crlf = CLUB_RIDES_ICS.replace('\n', "\r\n");
+        assert_eq!(parse_ics(&crlf).unwrap(), events);
+    }
+
+    #[test]
+    fn test_parse_invalid_ics() {
+        assert!(parse_ics("BEGIN:VEVENT\nSUMMARY:Ride\nEND:VEVENT\n").is_err());
+        assert!(parse_ics("BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Ride\n").is_err());
+        assert!(parse_ics("BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VCALENDAR\n").is_err());
+        assert!(parse_ics(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:tomorrow\nEND:VEVENT\nEND:VCALENDAR"
+        )
+        .is_err());
+        assert_eq!(
+            parse_ics("BEGIN:VCALENDAR\nEND:VCALENDAR").unwrap(),
+            Vec::new()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_report_events() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let events = parse_ics(CLUB_RIDES_ICS).unwrap();
+        let reports = report_events(&events, &routes);
+        let names: Vec<_> = reports
+            .iter()
+            .map(|report| report.route.map(|route| route.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Some("Tempus Fugit"),
+                Some("Road to Sky"),
+                Some("Tour of Fire & Ice"),
+                None
+            ]
+        );
+
+        // Two laps of Tempus Fugit and its lead-in
+        let social = &reports[0];
+        assert_eq!(social.laps, 2);
+        assert!((social.distance().unwrap().km() - 35.0).abs() < 1e-6);
+        assert!((social.elevation().unwrap().meters() - 34.0).abs() < 1e-6);
+        assert_eq!(social.badge_xp(), Some(340));
+
+        let climb = &reports[1];
+        assert_eq!(climb.laps, 1);
+        assert!((climb.distance().unwrap().km() - 18.4).abs() < 1e-6);
+        assert!((climb.elevation().unwrap().meters() - 1036.0).abs() < 1e-6);
+        assert_eq!(climb.badge_xp(), Some(700));
+
+        assert_eq!(reports[2].badge_xp(), None);
+        assert_eq!(reports[3].distance(), None);
+        assert_eq!(reports[3].elevation(), None);
+    }
+}
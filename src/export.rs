@@ -0,0 +1,318 @@
+//! Exports elevation profiles as GPX and TCX files for loading into other
+//! tools and bike computers.
+//!
+//! Profiles have no positions, so the track laps a circle inside the world's
+//! bounding box. Distances along the track match the profile, so elevation
+//! and grade line up with the route even though the shape doesn't.
+
+use std::{f64::consts::PI, fmt::Write, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    physics::Rider,
+    profile::ElevationProfile,
+    units::{Distance, Elevation},
+    world::World,
+};
+
+/// The mean radius of the earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+/// Meters per degree of latitude, and of longitude at the equator.
+const METERS_PER_DEGREE: f64 = EARTH_RADIUS * PI / 180.0;
+/// The longest gap between track points, so the straight lines between them
+/// stay close to the circle.
+const POINT_SPACING: f64 = 50.0;
+/// Garmin devices reject course names longer than this.
+const MAX_COURSE_NAME: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Gpx,
+    Tcx,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "gpx" => Ok(ExportFormat::Gpx),
+            "tcx" => Ok(ExportFormat::Tcx),
+            _ => Err(anyhow!(
+                "Unknown export format \"{s}\", expected gpx or tcx"
+            )),
+        }
+    }
+}
+
+/// A point on the exported track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrackPoint {
+    latitude: f64,
+    longitude: f64,
+    distance: Distance,
+    elevation: Elevation,
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Lays `profile` out on a circle in `world`, with points at every sample and
+/// at most `POINT_SPACING` apart in between.
+fn track_points(world: World, profile: &ElevationProfile) -> Vec<TrackPoint> {
+    let bounds = world.bounding_box();
+    let (center_latitude, center_longitude) = bounds.center();
+    let longitude_scale = METERS_PER_DEGREE * center_latitude.to_radians().cos();
+    let radius = f64::min(
+        (bounds.max_latitude - bounds.min_latitude) / 2.0 * METERS_PER_DEGREE,
+        (bounds.max_longitude - bounds.min_longitude) / 2.0 * longitude_scale,
+    ) * 0.9;
+
+    let samples = profile.samples();
+    let start = samples[0].0.meters();
+    let point = |meters: f64, elevation: f64| {
+        let angle = (meters - start) / radius;
+        TrackPoint {
+            latitude: center_latitude + radius * angle.sin() / METERS_PER_DEGREE,
+            longitude: center_longitude - radius * angle.cos() / longitude_scale,
+            distance: Distance::from_meters(meters - start),
+            elevation: Elevation::from_meters(elevation),
+        }
+    };
+
+    let mut points = vec![point(samples[0].0.meters(), samples[0].1.meters())];
+    for pair in samples.windows(2) {
+        let ((from, from_elevation), (to, to_elevation)) = (pair[0], pair[1]);
+        let length = to.meters() - from.meters();
+        let steps = (length / POINT_SPACING).ceil().max(1.0) as usize;
+        for step in 1..=steps {
+            let fraction = step as f64 / steps as f64;
+            points.push(point(
+                from.meters() + length * fraction,
+                from_elevation.meters()
+                    + (to_elevation.meters() - from_elevation.meters()) * fraction,
+            ));
+        }
+    }
+    points
+}
+
+/// Writes `profile` as a GPX 1.1 track in `world`.
+pub fn to_gpx(name: &str, world: World, profile: &ElevationProfile) -> String {
+    let name = escape_xml(name);
+    let mut gpx = String::new();
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str(
+        "<gpx version=\"1.1\" creator=\"zwift_data\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    let _ = writeln!(gpx, "  <metadata><name>{name}</name></metadata>");
+    gpx.push_str("  <trk>\n");
+    let _ = writeln!(gpx, "    <name>{name}</name>");
+    let _ = writeln!(gpx, "    <desc>{}</desc>", escape_xml(world.name()));
+    gpx.push_str("    <trkseg>\n");
+    for point in track_points(world, profile) {
+        let _ = writeln!(
+            gpx,
+            "      <trkpt lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.1}</ele></trkpt>",
+            point.latitude,
+            point.longitude,
+            point.elevation.meters()
+        );
+    }
+    gpx.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+    gpx
+}
+
+/// Writes `profile` as a TCX course in `world`, timed as ridden by `rider`
+/// starting at `start`, see [`Rider::ride_times`].
+pub fn to_tcx(
+    name: &str,
+    world: World,
+    profile: &ElevationProfile,
+    rider: &Rider,
+    start: DateTime<Utc>,
+) -> String {
+    let points = track_points(world, profile);
+
+    // Times between samples are interpolated from those the rider reaches
+    // the samples at
+    let samples = profile.samples();
+    let times = rider.ride_times(samples);
+    let offset = samples[0].0.meters();
+    let seconds: Vec<_> = points
+        .iter()
+        .map(|point| {
+            let meters = point.distance.meters() + offset;
+            let index = samples
+                .partition_point(|(distance, _)| distance.meters() < meters)
+                .clamp(1, samples.len() - 1);
+            let (from, to) = (samples[index - 1].0.meters(), samples[index].0.meters());
+            let (from_time, to_time) = (times[index - 1].as_secs_f64(), times[index].as_secs_f64());
+            if to > from {
+                from_time + (to_time - from_time) * (meters - from) / (to - from)
+            } else {
+                to_time
+            }
+        })
+        .collect();
+
+    let name = escape_xml(&name.chars().take(MAX_COURSE_NAME).collect::<String>());
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut tcx = String::new();
+    tcx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tcx.push_str(
+        "<TrainingCenterDatabase xmlns=\"http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2\">\n",
+    );
+    tcx.push_str("  <Courses>\n    <Course>\n");
+    let _ = writeln!(tcx, "      <Name>{name}</Name>");
+    tcx.push_str("      <Lap>\n");
+    let _ = writeln!(
+        tcx,
+        "        <TotalTimeSeconds>{:.1}</TotalTimeSeconds>",
+        seconds[seconds.len() - 1]
+    );
+    let _ = writeln!(
+        tcx,
+        "        <DistanceMeters>{:.1}</DistanceMeters>",
+        last.distance.meters()
+    );
+    for (tag, point) in [("BeginPosition", first), ("EndPosition", last)] {
+        let _ = writeln!(
+            tcx,
+            "        <{tag}><LatitudeDegrees>{:.7}</LatitudeDegrees><LongitudeDegrees>{:.7}</LongitudeDegrees></{tag}>",
+            point.latitude, point.longitude
+        );
+    }
+    tcx.push_str("        <Intensity>Active</Intensity>\n      </Lap>\n      <Track>\n");
+    for (point, seconds) in points.iter().zip(seconds) {
+        let time = start + chrono::Duration::milliseconds((seconds * 1000.0).round() as i64);
+        let _ = writeln!(
+            tcx,
+            "        <Trackpoint><Time>{}</Time><Position><LatitudeDegrees>{:.7}</LatitudeDegrees><LongitudeDegrees>{:.7}</LongitudeDegrees></Position><AltitudeMeters>{:.1}</AltitudeMeters><DistanceMeters>{:.1}</DistanceMeters></Trackpoint>",
+            time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            point.latitude,
+            point.longitude,
+            point.elevation.meters(),
+            point.distance.meters()
+        );
+    }
+    tcx.push_str("      </Track>\n    </Course>\n  </Courses>\n</TrainingCenterDatabase>\n");
+    tcx
+}
+
+/// The distance between two points on the earth's surface, in meters.
+#[cfg(test)]
+fn haversine(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat_a, lat_b) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{SecondsFormat, TimeZone, Utc};
+
+    use super::{escape_xml, haversine, to_gpx, to_tcx, track_points};
+    use crate::{physics::Rider, profile::ElevationProfile, world::World};
+
+    fn profile() -> ElevationProfile {
+        ElevationProfile::from_json("[[0, 14], [2.2, 12], [5.3, 54], [12.6, 790], [17.5, 1048]]")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("Fire & Ice <\"Tour\">"),
+            "Fire &amp; Ice &lt;&quot;Tour&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn test_track_points() {
+        for world in World::ALL {
+            let points = track_points(world, &profile());
+            let bounds = world.bounding_box();
+            assert!(points
+                .iter()
+                .all(|point| bounds.contains(point.latitude, point.longitude)));
+
+            // The track is as long as the profile, to within 0.1%
+            let length: f64 = points
+                .windows(2)
+                .map(|pair| {
+                    haversine(
+                        (pair[0].latitude, pair[0].longitude),
+                        (pair[1].latitude, pair[1].longitude),
+                    )
+                })
+                .sum();
+            assert!((length - 17_500.0).abs() < 17.5, "{world}: {length}");
+        }
+    }
+
+    #[test]
+    fn test_to_gpx() {
+        let gpx = to_gpx("Tour of Fire & Ice", World::Watopia, &profile());
+        assert!(gpx.contains("<name>Tour of Fire &amp; Ice</name>"));
+        assert!(gpx.contains("<desc>Watopia</desc>"));
+        assert_eq!(gpx.matches("<trkpt ").count(), 351);
+        assert!(gpx.contains("<ele>14.0</ele></trkpt>"));
+        assert!(gpx
+            .trim_end()
+            .ends_with("<ele>1048.0</ele></trkpt>\n    </trkseg>\n  </trk>\n</gpx>"));
+    }
+
+    #[test]
+    fn test_to_tcx() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 18, 8, 0, 0).unwrap();
+        let tcx = to_tcx(
+            "Road to Sky & Beyond",
+            World::Watopia,
+            &profile(),
+            &Rider::default(),
+            start,
+        );
+        assert!(tcx.contains("<Name>Road to Sky &amp; B</Name>"));
+        assert!(tcx.contains("<DistanceMeters>17500.0</DistanceMeters>"));
+        assert!(tcx.contains("<Time>2026-10-18T08:00:00Z</Time>"));
+        assert_eq!(tcx.matches("<Trackpoint>").count(), 351);
+
+        // Timed as the rider rides the profile
+        let seconds: f64 = tcx
+            .split("<TotalTimeSeconds>")
+            .nth(1)
+            .and_then(|rest| rest.split('<').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let times = Rider::default().ride_times(profile().samples());
+        assert_eq!(seconds, (times[4].as_secs_f64() * 10.0).round() / 10.0);
+
+        // The third sample, 5.3 km along, is reached when the rider gets there
+        let third = start
+            + chrono::Duration::milliseconds((times[2].as_secs_f64() * 1000.0).round() as i64);
+        assert!(tcx.contains(&format!(
+            "<Time>{}</Time>",
+            third.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )));
+    }
+}
@@ -1,12 +1,27 @@
-use std::vec;
+use std::{fmt, vec};
 
 use anyhow::{anyhow, Result};
-use async_recursion::async_recursion;
 use html_parser::{Dom, Element, Node};
 use log::debug;
 
+#[cfg(test)]
+mod conformance_test;
+mod document;
+#[cfg(test)]
+mod property_test;
+mod serialize;
+mod text;
+
+pub use document::{parse, Document};
+pub use serialize::{inner_html, node_html, outer_html, outer_html_pretty};
+pub use text::{decode_entities, text};
+
 pub async fn find<'a>(element: &'a Element, selectors_string: &str) -> Result<Vec<&'a Element>> {
-    find_elements(element, &parse_selector_string(selectors_string)?).await
+    let selectors = parse_selector_string(selectors_string)?;
+    let mut elements = Vec::new();
+    find_elements(element, &selectors, &mut Vec::new(), &mut elements);
+
+    Ok(elements)
 }
 
 pub async fn select<'a>(dom: &'a Dom, selectors_string: &str) -> Result<Vec<&'a Element>> {
@@ -14,7 +29,7 @@ pub async fn select<'a>(dom: &'a Dom, selectors_string: &str) -> Result<Vec<&'a
     let mut elements = Vec::new();
     for child in &dom.children {
         if let Node::Element(element) = child {
-            elements.append(&mut find_elements(element, &selectors).await?);
+            find_elements(element, &selectors, &mut Vec::new(), &mut elements);
         }
     }
 
@@ -29,6 +44,8 @@ enum BasicSelector {
     Class(String),
     IdWithClasses(String, Vec<String>),
     ElementWithClasses(String, Vec<String>),
+    /// A tag name, an ID and any classes, e.g. `div#myDiv.item`
+    ElementWithId(String, String, Vec<String>),
     ClassList(Vec<String>),
 }
 
@@ -45,77 +62,133 @@ impl Clone for BasicSelector {
             BasicSelector::ElementWithClasses(string, class_list) => {
                 BasicSelector::ElementWithClasses(string.clone(), class_list.clone())
             }
+            BasicSelector::ElementWithId(tag, id, class_list) => {
+                BasicSelector::ElementWithId(tag.clone(), id.clone(), class_list.clone())
+            }
             BasicSelector::ClassList(class_list) => BasicSelector::ClassList(class_list.clone()),
         }
     }
 }
 
+impl fmt::Display for BasicSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BasicSelector::All => write!(f, "*"),
+            BasicSelector::Id(id) => write!(f, "#{id}"),
+            BasicSelector::Element(tag) => write!(f, "{tag}"),
+            BasicSelector::Class(class) => write!(f, ".{class}"),
+            BasicSelector::IdWithClasses(id, class_list) => {
+                write!(f, "#{id}.{}", class_list.join("."))
+            }
+            BasicSelector::ElementWithClasses(tag, class_list) => {
+                write!(f, "{tag}.{}", class_list.join("."))
+            }
+            BasicSelector::ElementWithId(tag, id, class_list) => {
+                write!(f, "{tag}#{id}")?;
+                class_list
+                    .iter()
+                    .try_for_each(|class| write!(f, ".{class}"))
+            }
+            BasicSelector::ClassList(class_list) => write!(f, ".{}", class_list.join(".")),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum Selector {
     Basic(BasicSelector),
     Hierarchical(Vec<BasicSelector>),
 }
 
-fn parse_individual_selector_string(selector_string: &str) -> Result<BasicSelector> {
-    debug!("selector_string = {}", selector_string);
-    // Must not contain white space
-    assert!(!selector_string.contains(char::is_whitespace));
-    // Must also not contain . after first charactet
-    assert!(!selector_string[1..].contains('.'));
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Basic(basic_selector) => write!(f, "{basic_selector}"),
+            Selector::Hierarchical(basic_selectors) => {
+                let steps: Vec<_> = basic_selectors.iter().map(|s| s.to_string()).collect();
+                write!(f, "{}", steps.join(" "))
+            }
+        }
+    }
+}
+
+/// Checks that a tag, class or id name only contains characters this engine
+/// supports, so that unsupported CSS syntax is reported rather than silently
+/// matching nothing.
+fn validate_name(name: &str, kind: &str, selector_string: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(anyhow!(
+            "Invalid query string \"{selector_string}\": empty {kind} name"
+        ));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(anyhow!(
+            "Invalid query string \"{selector_string}\": {kind} name \"{name}\" cannot start with a digit"
+        ));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_' || !c.is_ascii()))
+    {
+        return Err(anyhow!(
+            "Invalid query string \"{selector_string}\": unsupported character '{c}'"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a compound selector such as `li`, `#myDiv`, `.item.extra`,
+/// `li.item` or `div#myDiv` that must match a single element.
+fn parse_compound_selector(selector_string: &str) -> Result<BasicSelector> {
     if selector_string == "*" {
         // No need to consider other selectors if * is specified
-        Ok(BasicSelector::All)
-    } else if let Some(class) = selector_string.strip_prefix('.') {
-        if class.is_empty() {
-            Err(anyhow!(
-                "Invalid query string \"{selector_string}\": Single '.' not valid"
-            ))
-        } else {
-            Ok(BasicSelector::Class(class.into()))
-        }
-    } else if let Some(id) = selector_string.strip_prefix('#') {
-        if id.is_empty() {
-            Err(anyhow!(
-                "Invalid query string \"{selector_string}\": Single '#' not valid"
-            ))
-        } else if id[1..].contains('#') || id[1..].contains('#') {
-            Err(anyhow!("Invalid query string \"{selector_string}\": selectors can only contain one element ID"))
+        return Ok(BasicSelector::All);
+    }
+
+    let tag_end = selector_string
+        .find(['.', '#'])
+        .unwrap_or(selector_string.len());
+    let (tag, mut rest) = selector_string.split_at(tag_end);
+    let mut id = None;
+    let mut classes = Vec::new();
+    while let Some(prefix) = rest.chars().next() {
+        let name_end = rest[1..].find(['.', '#']).map_or(rest.len(), |end| end + 1);
+        let name = &rest[1..name_end];
+        if prefix == '#' {
+            validate_name(name, "id", selector_string)?;
+            if id.is_some() {
+                return Err(anyhow!("Invalid query string \"{selector_string}\": selectors can only contain one element ID"));
+            }
+            id = Some(name.to_string());
         } else {
-            Ok(BasicSelector::Id(id.into()))
+            validate_name(name, "class", selector_string)?;
+            classes.push(name.to_string());
         }
-    } else {
-        Ok(BasicSelector::Element(selector_string.into()))
+        rest = &rest[name_end..];
     }
-}
 
-fn parese_complex_selector(selector_string: &str) -> Result<BasicSelector> {
-    assert!(!selector_string.contains(char::is_whitespace));
-    let mut class_parts: Vec<_> = selector_string.split('.').collect();
-    match class_parts.len() {
-        0 => Err(anyhow!("Invalid query string: {}", selector_string)),
-        1 => Ok(parse_individual_selector_string(selector_string)?),
-        _ => {
-            if class_parts.len() == 2 && class_parts[0].is_empty() {
-                // It's a single class selector
-                Ok(parse_individual_selector_string(selector_string)?)
+    match (tag, id) {
+        ("*", _) => Err(anyhow!(
+            "Invalid selector \"{selector_string}\": Selector cannot contain * and other selectors"
+        )),
+        ("", None) => match classes.len() {
+            1 => Ok(BasicSelector::Class(classes.remove(0))),
+            _ => Ok(BasicSelector::ClassList(classes)),
+        },
+        ("", Some(id)) if classes.is_empty() => Ok(BasicSelector::Id(id)),
+        ("", Some(id)) => Ok(BasicSelector::IdWithClasses(id, classes)),
+        (tag, None) => {
+            validate_name(tag, "tag", selector_string)?;
+            if classes.is_empty() {
+                Ok(BasicSelector::Element(tag.to_string()))
             } else {
-                // Determine what the first selector type is
-                let first_selector = if class_parts[0].is_empty() {
-                    class_parts = class_parts[1..].to_vec();
-                    format!(".{}", class_parts[1])
-                } else {
-                    class_parts[0].to_string()
-                };
-                let basic_selector = parse_individual_selector_string(&first_selector)?;
-                match basic_selector {
-                    BasicSelector::All => Err(anyhow!("Invalid selector \"{selector_string}\": Selector cannot contain * and other selectors")),
-                    BasicSelector::Element(element) => Ok(BasicSelector::ElementWithClasses(element, class_parts[1..].iter().map(|s| s.to_string()).collect())),
-                    BasicSelector::Id(id) => Ok(BasicSelector::IdWithClasses(id, class_parts[1..].iter().map(|s| s.to_string()).collect())),
-                    BasicSelector::Class(_) => Ok(BasicSelector::ClassList(class_parts.iter().map(|s| s.to_string()).collect())),
-                    BasicSelector::IdWithClasses(_, _) | BasicSelector::ElementWithClasses(_, _) | BasicSelector::ClassList(_) => Err(anyhow!("Internal parse error: {}", selector_string)),
-                }
+                Ok(BasicSelector::ElementWithClasses(tag.to_string(), classes))
             }
         }
+        (tag, Some(id)) => {
+            validate_name(tag, "tag", selector_string)?;
+            Ok(BasicSelector::ElementWithId(tag.to_string(), id, classes))
+        }
     }
 }
 
@@ -125,17 +198,16 @@ fn parse_selector_string(selector_string: &str) -> Result<Vec<Selector>> {
         let selector_strings: Vec<_> = item.split_ascii_whitespace().collect();
         match selector_strings.len() {
             0 => return Err(anyhow!("Invalid query string: {}", selector_string)),
-            1 => selectors.push(Selector::Basic(parese_complex_selector(
+            1 => selectors.push(Selector::Basic(parse_compound_selector(
                 selector_strings[0],
             )?)),
             _ => {
                 // White space seperated selectors are hierarchical.
                 let mut hierarchical_selectors = Vec::new();
                 for selector_string in selector_strings {
-                    let basic_selector = parese_complex_selector(selector_string)?;
+                    let basic_selector = parse_compound_selector(selector_string)?;
                     hierarchical_selectors.push(basic_selector);
                 }
-                assert!(hierarchical_selectors.len() > 1);
                 selectors.push(Selector::Hierarchical(hierarchical_selectors));
             }
         }
@@ -146,117 +218,314 @@ fn parse_selector_string(selector_string: &str) -> Result<Vec<Selector>> {
     Ok(selectors)
 }
 
-fn element_matches_basic_selector(element: &Element, basic_selector: &BasicSelector) -> bool {
-    match basic_selector {
-        BasicSelector::All => true,
-        BasicSelector::Id(id) => {
-            if let Some(element_id) = &element.id {
-                *id == *element_id
-            } else {
-                false
+/// A single reason an element failed to match a compound selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    Tag {
+        expected: String,
+        found: String,
+    },
+    Id {
+        expected: String,
+        found: Option<String>,
+    },
+    MissingClasses(Vec<String>),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Tag { expected, found } => {
+                write!(f, "expected tag <{expected}> but found <{found}>")
             }
-        }
-        BasicSelector::Element(tag) => *tag == element.name,
-        BasicSelector::Class(class) => element.classes.contains(class),
-        BasicSelector::IdWithClasses(id, class_list) => {
-            if let Some(element_id) = &element.id {
-                *id == *element_id
-                    && class_list
-                        .iter()
-                        .all(|class| element.classes.contains(class))
-            } else {
-                false
+            Mismatch::Id {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected id \"{expected}\" but found \"{found}\""),
+            Mismatch::Id {
+                expected,
+                found: None,
+            } => write!(f, "expected id \"{expected}\" but element has no id"),
+            Mismatch::MissingClasses(classes) => {
+                write!(f, "missing class(es) {}", classes.join(", "))
             }
         }
-        BasicSelector::ElementWithClasses(tag, class_list) => {
-            debug!("Checking if {element:#?} matches selector {basic_selector:?}");
-            *tag == element.name
-                && class_list
-                    .iter()
-                    .all(|class| element.classes.contains(class))
+    }
+}
+
+fn id_mismatch(element: &Element, id: &str) -> Option<Mismatch> {
+    match &element.id {
+        Some(element_id) if element_id == id => None,
+        found => Some(Mismatch::Id {
+            expected: id.to_string(),
+            found: found.clone(),
+        }),
+    }
+}
+
+fn tag_mismatch(element: &Element, tag: &str) -> Option<Mismatch> {
+    if element.name == tag {
+        None
+    } else {
+        Some(Mismatch::Tag {
+            expected: tag.to_string(),
+            found: element.name.clone(),
+        })
+    }
+}
+
+fn classes_mismatch(element: &Element, class_list: &[String]) -> Option<Mismatch> {
+    let missing: Vec<_> = class_list
+        .iter()
+        .filter(|class| !element.classes.contains(class))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        None
+    } else {
+        Some(Mismatch::MissingClasses(missing))
+    }
+}
+
+fn basic_selector_mismatches(element: &Element, basic_selector: &BasicSelector) -> Vec<Mismatch> {
+    match basic_selector {
+        BasicSelector::All => vec![],
+        BasicSelector::Id(id) => id_mismatch(element, id).into_iter().collect(),
+        BasicSelector::Element(tag) => tag_mismatch(element, tag).into_iter().collect(),
+        BasicSelector::Class(class) => classes_mismatch(element, std::slice::from_ref(class))
+            .into_iter()
+            .collect(),
+        BasicSelector::IdWithClasses(id, class_list) => id_mismatch(element, id)
+            .into_iter()
+            .chain(classes_mismatch(element, class_list))
+            .collect(),
+        BasicSelector::ElementWithClasses(tag, class_list) => tag_mismatch(element, tag)
+            .into_iter()
+            .chain(classes_mismatch(element, class_list))
+            .collect(),
+        BasicSelector::ElementWithId(tag, id, class_list) => tag_mismatch(element, tag)
+            .into_iter()
+            .chain(id_mismatch(element, id))
+            .chain(classes_mismatch(element, class_list))
+            .collect(),
+        BasicSelector::ClassList(class_list) => {
+            classes_mismatch(element, class_list).into_iter().collect()
         }
-        BasicSelector::ClassList(class_list) => class_list
-            .iter()
-            .all(|class| element.classes.contains(class)),
     }
 }
 
-#[async_recursion]
-async fn find_elements_for_selector<'a>(
+fn element_matches_basic_selector(element: &Element, basic_selector: &BasicSelector) -> bool {
+    let mismatches = basic_selector_mismatches(element, basic_selector);
+    debug!(
+        "<{}> matches selector {basic_selector}: {}",
+        element.name,
+        mismatches.is_empty()
+    );
+    mismatches.is_empty()
+}
+
+/// Collects `element` and its descendants that match any of `selectors`, in
+/// document order and without duplicates.
+fn find_elements<'a>(
     element: &'a Element,
-    selector: &Selector,
-) -> Result<Vec<&'a Element>> {
-    match selector {
-        Selector::Basic(basic_selector) => {
-            if element_matches_basic_selector(element, basic_selector) {
-                Ok(vec![element])
-            } else {
-                Ok(vec![])
-            }
+    selectors: &[Selector],
+    ancestors: &mut Vec<&'a Element>,
+    elements: &mut Vec<&'a Element>,
+) {
+    if selectors
+        .iter()
+        .any(|selector| element_matches_selector(element, ancestors, selector))
+    {
+        elements.push(element);
+    }
+
+    ancestors.push(element);
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            find_elements(child, selectors, ancestors, elements);
         }
-        Selector::Hierarchical(basic_selectors) => {
-            if element_matches_basic_selector(element, &basic_selectors[0]) {
-                if basic_selectors.len() == 1 {
-                    Ok(vec![element])
-                } else {
-                    let hierarchical_selector =
-                        Selector::Hierarchical(basic_selectors.clone()[1..].to_vec());
-                    let mut elements = Vec::new();
-                    for child in &element.children {
-                        if let Node::Element(element) = child {
-                            elements.append(
-                                &mut find_elements_for_selector(element, &hierarchical_selector)
-                                    .await?,
-                            );
-                        }
-                    }
-
-                    Ok(elements)
-                }
-            } else {
-                let mut elements = Vec::new();
-                for child in &element.children {
-                    if let Node::Element(element) = child {
-                        elements.append(&mut find_elements_for_selector(element, selector).await?);
-                    }
-                }
+    }
+    ancestors.pop();
+}
 
-                Ok(elements)
+/// Outcome of matching one comma-separated selector against an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchResult {
+    Matched,
+    /// The rightmost compound selector did not match the element itself.
+    SubjectMismatch {
+        step: String,
+        mismatches: Vec<Mismatch>,
+    },
+    /// The element matched up to `step`, but no ancestor matched `step`.
+    NoMatchingAncestor {
+        step: String,
+    },
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchResult::Matched => write!(f, "matched"),
+            MatchResult::SubjectMismatch { step, mismatches } => {
+                let reasons: Vec<_> = mismatches.iter().map(|m| m.to_string()).collect();
+                write!(f, "step \"{step}\" failed: {}", reasons.join("; "))
+            }
+            MatchResult::NoMatchingAncestor { step } => {
+                write!(f, "step \"{step}\" failed: no ancestor matches")
             }
         }
     }
 }
 
-#[async_recursion]
-async fn find_elements<'a>(
-    element: &'a Element,
-    selectors: &Vec<Selector>,
-) -> Result<Vec<&'a Element>> {
-    let mut elements = Vec::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorExplanation {
+    pub selector: String,
+    pub result: MatchResult,
+}
 
-    for selector in selectors {
-        let matching_elements = find_elements_for_selector(element, selector).await?;
-        for matching_element in matching_elements {
-            if !elements.contains(&matching_element) {
-                elements.push(matching_element);
-            }
+/// Why a single element in the document did or did not match a selector string.
+#[derive(Debug)]
+pub struct ElementExplanation<'a> {
+    pub element: &'a Element,
+    /// Path from the document root, e.g. `div#myDiv ul li.item`.
+    pub path: String,
+    pub selectors: Vec<SelectorExplanation>,
+}
+
+impl<'a> ElementExplanation<'a> {
+    pub fn matched(&self) -> bool {
+        self.selectors
+            .iter()
+            .any(|explanation| explanation.result == MatchResult::Matched)
+    }
+}
+
+impl<'a> fmt::Display for ElementExplanation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path)?;
+        for explanation in &self.selectors {
+            writeln!(f, "  \"{}\": {}", explanation.selector, explanation.result)?;
         }
+        Ok(())
     }
+}
 
-    for child in &element.children {
+/// Explains, for every element in `dom`, which step of each selector in
+/// `selectors_string` matched or failed.
+pub async fn explain<'a>(
+    dom: &'a Dom,
+    selectors_string: &str,
+) -> Result<Vec<ElementExplanation<'a>>> {
+    let selectors = parse_selector_string(selectors_string)?;
+    let mut explanations = Vec::new();
+    let mut ancestors = Vec::new();
+    for child in &dom.children {
         if let Node::Element(element) = child {
-            elements.append(&mut find_elements(element, selectors).await?);
+            explain_elements(element, &selectors, &mut ancestors, &mut explanations);
         }
     }
 
-    Ok(elements)
+    Ok(explanations)
+}
+
+fn describe_element(element: &Element) -> String {
+    let mut description = element.name.clone();
+    if let Some(id) = &element.id {
+        description.push('#');
+        description.push_str(id);
+    }
+    for class in &element.classes {
+        description.push('.');
+        description.push_str(class);
+    }
+    description
+}
+
+fn explain_selector(element: &Element, ancestors: &[&Element], selector: &Selector) -> MatchResult {
+    let basic_selectors = match selector {
+        Selector::Basic(basic_selector) => std::slice::from_ref(basic_selector),
+        Selector::Hierarchical(basic_selectors) => basic_selectors.as_slice(),
+    };
+    let (subject, rest) = basic_selectors
+        .split_last()
+        .expect("selectors always have at least one step");
+
+    let mismatches = basic_selector_mismatches(element, subject);
+    if !mismatches.is_empty() {
+        return MatchResult::SubjectMismatch {
+            step: subject.to_string(),
+            mismatches,
+        };
+    }
+
+    // Descendant combinators: match the remaining steps right to left against
+    // the nearest ancestors that satisfy them.
+    let mut remaining = rest.iter().rev().peekable();
+    for ancestor in ancestors.iter().rev() {
+        match remaining.peek() {
+            Some(step) if element_matches_basic_selector(ancestor, step) => {
+                remaining.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    match remaining.next() {
+        Some(step) => MatchResult::NoMatchingAncestor {
+            step: step.to_string(),
+        },
+        None => MatchResult::Matched,
+    }
+}
+
+fn element_matches_selector(
+    element: &Element,
+    ancestors: &[&Element],
+    selector: &Selector,
+) -> bool {
+    explain_selector(element, ancestors, selector) == MatchResult::Matched
+}
+
+fn explain_elements<'a>(
+    element: &'a Element,
+    selectors: &[Selector],
+    ancestors: &mut Vec<&'a Element>,
+    explanations: &mut Vec<ElementExplanation<'a>>,
+) {
+    let path = ancestors
+        .iter()
+        .chain(std::iter::once(&element))
+        .map(|e| describe_element(e))
+        .collect::<Vec<_>>()
+        .join(" ");
+    explanations.push(ElementExplanation {
+        element,
+        path,
+        selectors: selectors
+            .iter()
+            .map(|selector| SelectorExplanation {
+                selector: selector.to_string(),
+                result: explain_selector(element, ancestors, selector),
+            })
+            .collect(),
+    });
+
+    ancestors.push(element);
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            explain_elements(child, selectors, ancestors, explanations);
+        }
+    }
+    ancestors.pop();
 }
 
 #[cfg(test)]
 mod test {
     use html_parser::{Dom, Node};
 
-    use super::{find, select};
+    use super::{explain, find, select, MatchResult, Mismatch};
 
     static TEST_HTML: &str = r#"<div id="myDiv">
   <h1 class="title">Title</h1>
@@ -285,6 +554,8 @@ mod test {
     //  "#myDiv li.item"    <li class="item">Item 1</li>, <li class="item extra">Item 2</li>
     //  "li.item.extra"     <li class="item extra">Item 2</li>
     //  ".item.extra"       <li class="item extra">Item 2</li>
+    //  "div#myDiv"         <div id="myDiv">...</div>
+    //  "div#myDiv li"      <li class="item">Item 1</li>, <li class="item extra">Item 2</li>
     */
 
     #[tokio::test]
@@ -434,6 +705,18 @@ mod test {
         assert_eq!(elements[0].name, "li");
         assert!(elements[0].classes.contains(&"item".to_string()));
         assert_eq!(elements[0].children[0], Node::Text("Item 2".to_string()));
+
+        //  "div#myDiv"         <div id="myDiv">...</div>
+        let elements = select(&dom, "div#myDiv").await.unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements[0].id, Some("myDiv".to_string()));
+        assert!(select(&dom, "p#myDiv").await.unwrap().is_empty());
+        assert!(select(&dom, "div#myDiv.intro").await.unwrap().is_empty());
+
+        //  "div#myDiv li"      <li class="item">Item 1</li>, <li class="item extra">Item 2</li>
+        let elements = select(&dom, "div#myDiv li").await.unwrap();
+        assert_eq!(elements.len(), 2);
+        assert!(elements.iter().all(|element| element.name == "li"));
     }
 
     #[tokio::test]
@@ -490,4 +773,94 @@ mod test {
         assert!(elements[1].classes.contains(&"item".to_string()));
         assert_eq!(elements[1].children[0], Node::Text("Item 2".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_explain() {
+        let dom = Dom::parse(TEST_HTML).unwrap();
+
+        let explanations = explain(&dom, "#myDiv li.extra").await.unwrap();
+        assert_eq!(explanations.len(), 6);
+
+        let matched: Vec<_> = explanations.iter().filter(|e| e.matched()).collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].path, "div#myDiv ul li.item.extra");
+
+        let item1 = explanations
+            .iter()
+            .find(|e| e.path == "div#myDiv ul li.item")
+            .unwrap();
+        assert_eq!(
+            item1.selectors[0].result,
+            MatchResult::SubjectMismatch {
+                step: "li.extra".to_string(),
+                mismatches: vec![Mismatch::MissingClasses(vec!["extra".to_string()])],
+            }
+        );
+
+        let h1 = explanations
+            .iter()
+            .find(|e| e.element.name == "h1")
+            .unwrap();
+        assert_eq!(
+            h1.selectors[0].result,
+            MatchResult::SubjectMismatch {
+                step: "li.extra".to_string(),
+                mismatches: vec![
+                    Mismatch::Tag {
+                        expected: "li".to_string(),
+                        found: "h1".to_string()
+                    },
+                    Mismatch::MissingClasses(vec!["extra".to_string()])
+                ],
+            }
+        );
+
+        let explanations = explain(&dom, "#other li, ul").await.unwrap();
+        let item2 = explanations
+            .iter()
+            .find(|e| e.path == "div#myDiv ul li.item.extra")
+            .unwrap();
+        assert!(!item2.matched());
+        assert_eq!(
+            item2.selectors[0].result,
+            MatchResult::NoMatchingAncestor {
+                step: "#other".to_string()
+            }
+        );
+        assert_eq!(item2.selectors[1].selector, "ul");
+        assert!(explanations
+            .iter()
+            .any(|e| e.element.name == "ul" && e.matched()));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_selectors() {
+        let dom = Dom::parse(TEST_HTML).unwrap();
+
+        for selector in [
+            "", " ", "h1,", "a..b", "li.", "#", ".", "#a#b", "div#a#b", "div#2a", "ul > li",
+            "*.item", "2a",
+        ] {
+            assert!(
+                select(&dom, selector).await.is_err(),
+                "\"{selector}\" should be rejected"
+            );
+        }
+
+        // Multi-byte characters must not be sliced mid character
+        assert!(select(&dom, "é").await.unwrap().is_empty());
+        assert!(select(&dom, "#é.ü").await.unwrap().is_empty());
+        assert!(select(&dom, "li\u{a0}.item").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nested_matches_are_not_duplicated() {
+        let dom = Dom::parse(include_str!("../test.html")).unwrap();
+
+        let elements = select(&dom, ".class1 .class2 .class3").await.unwrap();
+        assert_eq!(elements.len(), 6);
+        for (i, element) in elements.iter().enumerate() {
+            assert!(!elements[i + 1..].iter().any(|e| std::ptr::eq(*e, *element)));
+        }
+    }
 }
@@ -0,0 +1,133 @@
+//! Selector conformance cases adapted from the web-platform-tests
+//! `css/selectors` and `dom/nodes/ParentNode-querySelector-All` suites.
+//!
+//! The fixtures live in `fixtures/wpt`. Each case is run against [`select`]
+//! and the results are written up as a pass/fail matrix in
+//! `docs/selector-conformance.md`. Run with `UPDATE_CONFORMANCE=1` to
+//! regenerate the matrix after changing the selector engine.
+
+use std::collections::BTreeMap;
+
+use html_parser::Dom;
+use serde::Deserialize;
+
+use super::select;
+
+static FIXTURE_HTML: &str = include_str!("../../fixtures/wpt/selectors.html");
+static FIXTURE_CASES: &str = include_str!("../../fixtures/wpt/cases.json");
+const MATRIX_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/docs/selector-conformance.md");
+
+#[derive(Debug, Deserialize)]
+struct Case {
+    feature: String,
+    name: String,
+    selector: String,
+    expected: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Pass,
+    Rejected,
+    WrongElements,
+}
+
+impl Outcome {
+    fn description(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Rejected => "fail (selector rejected)",
+            Outcome::WrongElements => "fail (wrong elements)",
+        }
+    }
+}
+
+async fn run_case(dom: &Dom, case: &Case) -> Outcome {
+    match select(dom, &case.selector).await {
+        Ok(elements) => {
+            let ids: Vec<_> = elements
+                .iter()
+                .map(|element| element.id.clone().unwrap_or_default())
+                .collect();
+            if ids == case.expected {
+                Outcome::Pass
+            } else {
+                Outcome::WrongElements
+            }
+        }
+        Err(_) => Outcome::Rejected,
+    }
+}
+
+fn markdown_code(selector: &str) -> String {
+    let escaped = selector
+        .escape_debug()
+        .to_string()
+        .replace("\\\"", "\"")
+        .replace("\\'", "'")
+        .replace('|', "\\|");
+    format!("`{escaped}`")
+}
+
+fn matrix(results: &[(Case, Outcome)]) -> String {
+    let mut features: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for (case, outcome) in results {
+        let (passed, total) = features.entry(&case.feature).or_default();
+        *total += 1;
+        if *outcome == Outcome::Pass {
+            *passed += 1;
+        }
+    }
+
+    let mut markdown = String::from(
+        "# Selector conformance\n\
+         \n\
+         Results of running `html_query::select` against selector cases adapted\n\
+         from the web-platform-tests `css/selectors` and\n\
+         `dom/nodes/ParentNode-querySelector-All` suites. The fixtures are in\n\
+         `fixtures/wpt`. This file is generated by the conformance test; run\n\
+         `UPDATE_CONFORMANCE=1 cargo test conformance` to regenerate it.\n\
+         \n\
+         | Feature | Passed | Total |\n\
+         |---|---|---|\n",
+    );
+    for (feature, (passed, total)) in &features {
+        markdown.push_str(&format!("| {feature} | {passed} | {total} |\n"));
+    }
+
+    markdown.push_str("\n| Feature | Test | Selector | Result |\n|---|---|---|---|\n");
+    for (case, outcome) in results {
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            case.feature,
+            case.name,
+            markdown_code(&case.selector),
+            outcome.description()
+        ));
+    }
+
+    markdown
+}
+
+#[tokio::test]
+async fn conformance() {
+    let dom = Dom::parse(FIXTURE_HTML).unwrap();
+    let cases: Vec<Case> = serde_json::from_str(FIXTURE_CASES).unwrap();
+
+    let mut results = Vec::new();
+    for case in cases {
+        let outcome = run_case(&dom, &case).await;
+        results.push((case, outcome));
+    }
+    let matrix = matrix(&results);
+
+    if std::env::var_os("UPDATE_CONFORMANCE").is_some() {
+        std::fs::write(MATRIX_PATH, &matrix).unwrap();
+    } else {
+        let published = std::fs::read_to_string(MATRIX_PATH).unwrap_or_default();
+        assert!(
+            published == matrix,
+            "Selector conformance changed, rerun with UPDATE_CONFORMANCE=1 and review {MATRIX_PATH}"
+        );
+    }
+}
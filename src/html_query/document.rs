@@ -0,0 +1,463 @@
+use anyhow::Result;
+use html_parser::{Dom, Element, Node};
+
+use super::{
+    element_matches_selector, parse_selector_string,
+    serialize::{self, AttributeOrder},
+    Selector,
+};
+
+/// Parses `html` like `Dom::parse`, but keeps the text of comments verbatim.
+/// html_parser trims the whitespace inside comments, so `<!-- ad -->` would
+/// otherwise serialize as `<!--ad-->`.
+pub fn parse(html: &str) -> Result<Dom> {
+    let mut dom = Dom::parse(html)?;
+    let comments = source_comments(html);
+    restore_comments(&mut dom.children, &comments, &mut 0);
+    Ok(dom)
+}
+
+/// The text of each `<!-- -->` comment in `html`, in order.
+fn source_comments(html: &str) -> Vec<&str> {
+    let mut comments = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<!--") {
+        rest = &rest[start + 4..];
+        let Some(end) = rest.find("-->") else {
+            break;
+        };
+        comments.push(&rest[..end]);
+        rest = &rest[end + 3..];
+    }
+    comments
+}
+
+/// Replaces each parsed comment with the next source comment it was trimmed
+/// from. Comments without one, such as conditional comments, are left as
+/// parsed.
+fn restore_comments(nodes: &mut [Node], comments: &[&str], next: &mut usize) {
+    for node in nodes {
+        match node {
+            Node::Comment(comment) => {
+                if let Some(offset) = comments[*next..]
+                    .iter()
+                    .position(|source| source.trim() == comment.as_str())
+                {
+                    *next += offset;
+                    *comment = comments[*next].to_string();
+                    *next += 1;
+                }
+            }
+            Node::Element(element) => restore_comments(&mut element.children, comments, next),
+            Node::Text(_) => {}
+        }
+    }
+}
+
+/// A start tag in the source: its name and attribute names, in order.
+struct StartTag<'a> {
+    name: &'a str,
+    attributes: Vec<&'a str>,
+}
+
+/// The start tags in `html`, in order. Comments and the content of `script`
+/// and `style` elements are skipped.
+fn source_start_tags(html: &str) -> Vec<StartTag<'_>> {
+    let mut tags = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        rest = &rest[end..];
+        let mut attributes = Vec::new();
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+            if rest.is_empty() || rest.starts_with('>') {
+                break;
+            }
+            let end = rest
+                .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                // A stray "=", skip it
+                rest = &rest[1..];
+                continue;
+            }
+            attributes.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+            if let Some(value) = rest.strip_prefix('=') {
+                let value = value.trim_start();
+                rest = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        value[1..].find(quote).map_or("", |end| &value[end + 2..])
+                    }
+                    _ => {
+                        let end = value
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(value.len());
+                        &value[end..]
+                    }
+                };
+            }
+        }
+
+        if ["script", "style"]
+            .iter()
+            .any(|raw| name.eq_ignore_ascii_case(raw))
+        {
+            let close = format!("</{}", name.to_ascii_lowercase());
+            rest = rest
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or("", |end| &rest[end..]);
+        }
+        tags.push(StartTag { name, attributes });
+    }
+    tags
+}
+
+/// Pairs each parsed element with the next source start tag it could have
+/// been parsed from, and takes the attribute order from that tag. Elements
+/// without one get an empty order.
+fn attribute_orders(nodes: &[Node], tags: &[StartTag], next: &mut usize) -> Vec<AttributeOrder> {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Element(element) => {
+                let mut order = AttributeOrder::default();
+                if let Some(offset) = tags[*next..]
+                    .iter()
+                    .position(|tag| is_parsed_from(element, tag))
+                {
+                    *next += offset;
+                    order.names = tags[*next]
+                        .attributes
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect();
+                    *next += 1;
+                }
+                order.children = attribute_orders(&element.children, tags, next);
+                order
+            }
+            Node::Text(_) | Node::Comment(_) => AttributeOrder::default(),
+        })
+        .collect()
+}
+
+fn is_parsed_from(element: &Element, tag: &StartTag) -> bool {
+    let has = |name: &str| tag.attributes.contains(&name);
+    element.name.eq_ignore_ascii_case(tag.name)
+        && (element.id.is_none() || has("id"))
+        && (element.classes.is_empty() || has("class"))
+        && element.attributes.keys().all(|name| has(name))
+}
+
+/// An owned HTML document that can be modified with selectors and serialized
+/// back out, e.g. to strip ads and scripts from a scraped page:
+///
+/// ```no_run
+/// # async fn clean(html: &str) -> anyhow::Result<String> {
+/// use zwift_data::html_query::Document;
+///
+/// let mut document = Document::parse(html)?;
+/// document.remove("script,style,iframe,nav,.advertisement").await?;
+/// document.unwrap("font").await?;
+/// Ok(document.html())
+/// # }
+/// ```
+///
+/// Parsing with [`Document::parse`] keeps the source order of attributes,
+/// which `html_parser` loses. Attributes added later are written last.
+#[derive(Debug, Clone)]
+pub struct Document {
+    dom: Dom,
+    /// Attribute orders for `dom.children`, with the same shape.
+    attribute_orders: Vec<AttributeOrder>,
+}
+
+impl From<Dom> for Document {
+    fn from(dom: Dom) -> Self {
+        let attribute_orders = attribute_orders(&dom.children, &[], &mut 0);
+        Self {
+            dom,
+            attribute_orders,
+        }
+    }
+}
+
+impl Document {
+    pub fn parse(html: &str) -> Result<Self> {
+        let dom = parse(html)?;
+        let tags = source_start_tags(html);
+        let attribute_orders = attribute_orders(&dom.children, &tags, &mut 0);
+        Ok(Self {
+            dom,
+            attribute_orders,
+        })
+    }
+
+    /// The underlying DOM, for use with [`super::select`] and friends.
+    pub fn dom(&self) -> &Dom {
+        &self.dom
+    }
+
+    pub fn into_dom(self) -> Dom {
+        self.dom
+    }
+
+    /// Removes all matching elements along with their content. Returns the
+    /// number of elements removed.
+    pub async fn remove(&mut self, selectors_string: &str) -> Result<usize> {
+        let paths = self.matching_paths(selectors_string)?;
+        // Work backwards through the document so that earlier paths stay valid
+        for path in paths.iter().rev() {
+            let (index, parent_path) = path.split_last().unwrap();
+            children_at_mut(&mut self.dom.children, parent_path).remove(*index);
+            orders_at_mut(&mut self.attribute_orders, parent_path).remove(*index);
+        }
+
+        Ok(paths.len())
+    }
+
+    /// Replaces all matching elements with their children. Returns the number
+    /// of elements unwrapped.
+    pub async fn unwrap(&mut self, selectors_string: &str) -> Result<usize> {
+        let paths = self.matching_paths(selectors_string)?;
+        for path in paths.iter().rev() {
+            let (index, parent_path) = path.split_last().unwrap();
+            let siblings = children_at_mut(&mut self.dom.children, parent_path);
+            if let Node::Element(element) = siblings.remove(*index) {
+                siblings.splice(*index..*index, element.children);
+            }
+            let orders = orders_at_mut(&mut self.attribute_orders, parent_path);
+            let order = orders.remove(*index);
+            orders.splice(*index..*index, order.children);
+        }
+
+        Ok(paths.len())
+    }
+
+    /// Sets an attribute on all matching elements. A `None` value sets a
+    /// boolean attribute such as `hidden`. Returns the number of elements
+    /// changed.
+    pub async fn set_attr(
+        &mut self,
+        selectors_string: &str,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<usize> {
+        self.update(selectors_string, |element, _| match name {
+            "id" => element.id = value.map(str::to_string),
+            "class" => {
+                element.classes = value
+                    .unwrap_or_default()
+                    .split_ascii_whitespace()
+                    .map(str::to_string)
+                    .collect()
+            }
+            _ => {
+                element
+                    .attributes
+                    .insert(name.to_string(), value.map(str::to_string));
+            }
+        })
+    }
+
+    /// Adds a class to all matching elements that do not already have it.
+    /// Returns the number of elements matched.
+    pub async fn add_class(&mut self, selectors_string: &str, class: &str) -> Result<usize> {
+        self.update(selectors_string, |element, _| {
+            if !element.classes.iter().any(|c| c == class) {
+                element.classes.push(class.to_string());
+            }
+        })
+    }
+
+    /// Replaces the content of all matching elements with `text`. Returns the
+    /// number of elements changed.
+    pub async fn replace_text(&mut self, selectors_string: &str, text: &str) -> Result<usize> {
+        self.update(selectors_string, |element, order| {
+            element.children = vec![Node::Text(text.to_string())];
+            order.children = vec![AttributeOrder::default()];
+        })
+    }
+
+    pub fn html(&self) -> String {
+        serialize::nodes_html(&self.dom.children, &self.attribute_orders, None)
+    }
+
+    pub fn pretty_html(&self, indent: usize) -> String {
+        serialize::nodes_html(&self.dom.children, &self.attribute_orders, Some(indent))
+    }
+
+    fn update<F>(&mut self, selectors_string: &str, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&mut Element, &mut AttributeOrder),
+    {
+        let paths = self.matching_paths(selectors_string)?;
+        for path in &paths {
+            let (index, parent_path) = path.split_last().unwrap();
+            if let Node::Element(element) =
+                &mut children_at_mut(&mut self.dom.children, parent_path)[*index]
+            {
+                let order = &mut orders_at_mut(&mut self.attribute_orders, parent_path)[*index];
+                f(element, order);
+            }
+        }
+
+        Ok(paths.len())
+    }
+
+    /// Child index paths of all matching elements, in document order.
+    fn matching_paths(&self, selectors_string: &str) -> Result<Vec<Vec<usize>>> {
+        let selectors = parse_selector_string(selectors_string)?;
+        let mut paths = Vec::new();
+        collect_matching_paths(
+            &self.dom.children,
+            &selectors,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut paths,
+        );
+
+        Ok(paths)
+    }
+}
+
+fn collect_matching_paths<'a>(
+    nodes: &'a [Node],
+    selectors: &[Selector],
+    ancestors: &mut Vec<&'a Element>,
+    path: &mut Vec<usize>,
+    paths: &mut Vec<Vec<usize>>,
+) {
+    for (index, node) in nodes.iter().enumerate() {
+        if let Node::Element(element) = node {
+            path.push(index);
+            if selectors
+                .iter()
+                .any(|selector| element_matches_selector(element, ancestors, selector))
+            {
+                paths.push(path.clone());
+            }
+            ancestors.push(element);
+            collect_matching_paths(&element.children, selectors, ancestors, path, paths);
+            ancestors.pop();
+            path.pop();
+        }
+    }
+}
+
+fn children_at_mut<'a>(nodes: &'a mut Vec<Node>, path: &[usize]) -> &'a mut Vec<Node> {
+    match path.split_first() {
+        None => nodes,
+        Some((index, rest)) => match &mut nodes[*index] {
+            Node::Element(element) => children_at_mut(&mut element.children, rest),
+            node => panic!("Path does not lead to an element: {node:?}"),
+        },
+    }
+}
+
+fn orders_at_mut<'a>(
+    orders: &'a mut Vec<AttributeOrder>,
+    path: &[usize],
+) -> &'a mut Vec<AttributeOrder> {
+    match path.split_first() {
+        None => orders,
+        Some((index, rest)) => orders_at_mut(&mut orders[*index].children, rest),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Document;
+    use crate::html_query::select;
+
+    static TEST_HTML: &str = r#"<div id="content">
+  <nav><a href="/">Home</a></nav>
+  <script>track();</script>
+  <div class="ad"><img src="pixel.gif"></div>
+  <table>
+    <tr><td><span>Tempus Fugit</span></td><td><font>17.3km</font></td></tr>
+  </table>
+</div>"#;
+
+    #[tokio::test]
+    async fn test_remove_and_unwrap() {
+        let mut document = Document::parse(TEST_HTML).unwrap();
+
+        assert_eq!(document.remove("nav,script,.ad").await.unwrap(), 3);
+        assert_eq!(document.unwrap("td span, font").await.unwrap(), 2);
+        assert_eq!(document.remove("iframe").await.unwrap(), 0);
+
+        assert_eq!(
+            document.html(),
+            r#"<div id="content"><table><tr><td>Tempus Fugit</td><td>17.3km</td></tr></table></div>"#
+        );
+        assert!(select(document.dom(), "img").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remove_nested_matches() {
+        let mut document =
+            Document::parse("<div class=\"a\"><div class=\"a\"><p>x</p></div></div><p>y</p>")
+                .unwrap();
+        assert_eq!(document.remove(".a").await.unwrap(), 2);
+        assert_eq!(document.html(), "<p>y</p>");
+    }
+
+    #[tokio::test]
+    async fn test_attributes_and_text() {
+        let mut document = Document::parse(TEST_HTML).unwrap();
+
+        assert_eq!(document.add_class("td", "cell").await.unwrap(), 2);
+        assert_eq!(document.add_class("td", "cell").await.unwrap(), 2);
+        document
+            .set_attr("table", "id", Some("routes"))
+            .await
+            .unwrap();
+        document
+            .set_attr("table", "data-source", Some("zwift \"insider\""))
+            .await
+            .unwrap();
+        document.set_attr(".ad", "hidden", None).await.unwrap();
+        document.replace_text("a", "Start").await.unwrap();
+
+        let html = document.html();
+        assert!(html.contains(r#"<table id="routes" data-source="zwift &quot;insider&quot;">"#));
+        assert!(html.contains(r#"<td class="cell"><span>"#));
+        assert!(html.contains(r#"<div class="ad" hidden>"#));
+        assert!(html.contains(r#"<a href="/">Start</a>"#));
+        assert_eq!(select(document.dom(), "#routes").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_attribute_order() {
+        let html = r#"<div class="route" id="r1"><!-- <b c="1" a="2"> --><script>let x = "<a z=1 y=2>";</script><span data-b="2" data-a='1'>x</span><a target="_blank" href="https://zwiftinsider.com/route/tempus-fugit/" rel=nofollow hidden>Tempus Fugit</a></div>"#;
+        let mut document = Document::parse(html).unwrap();
+        assert_eq!(
+            document.html(),
+            html.replace("'1'", "\"1\"")
+                .replace("=nofollow", "=\"nofollow\"")
+        );
+
+        assert_eq!(document.unwrap("span").await.unwrap(), 1);
+        document.set_attr("a", "class", Some("link")).await.unwrap();
+        document.set_attr("a", "href", Some("/")).await.unwrap();
+        assert!(document.html().ends_with(
+            r#"x<a target="_blank" href="/" rel="nofollow" hidden class="link">Tempus Fugit</a></div>"#
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+//! Property based tests for the selector engine. Random selectors must never
+//! panic the parser, and selectors from the supported grammar must select the
+//! same elements as a browser grade CSS engine (`scraper`) on the same
+//! randomly generated documents.
+
+use html_parser::{Dom, Element};
+use proptest::prelude::*;
+
+use super::{explain, find, select, Document};
+
+const TAGS: &[&str] = &["div", "span", "section", "article", "header", "footer"];
+const IDS: &[&str] = &["a", "b", "c"];
+const CLASSES: &[&str] = &["x", "y", "z"];
+
+#[derive(Debug, Clone)]
+struct TestElement {
+    tag: &'static str,
+    id: Option<&'static str>,
+    classes: Vec<&'static str>,
+    children: Vec<TestElement>,
+}
+
+impl TestElement {
+    /// Writes the element as HTML, tagging each element with a unique
+    /// `data-n` attribute so results from both engines can be compared.
+    fn write_html(&self, html: &mut String, counter: &mut usize) {
+        html.push_str(&format!("<{} data-n=\"{counter}\"", self.tag));
+        *counter += 1;
+        if let Some(id) = self.id {
+            html.push_str(&format!(" id=\"{id}\""));
+        }
+        if !self.classes.is_empty() {
+            html.push_str(&format!(" class=\"{}\"", self.classes.join(" ")));
+        }
+        html.push('>');
+        for child in &self.children {
+            child.write_html(html, counter);
+        }
+        html.push_str(&format!("</{}>", self.tag));
+    }
+}
+
+fn document_html(elements: &[TestElement]) -> String {
+    let mut html = String::new();
+    let mut counter = 0;
+    for element in elements {
+        element.write_html(&mut html, &mut counter);
+    }
+    html
+}
+
+fn element_strategy() -> impl Strategy<Value = TestElement> {
+    let leaf = (
+        prop::sample::select(TAGS),
+        prop::option::of(prop::sample::select(IDS)),
+        prop::sample::subsequence(CLASSES, 0..=CLASSES.len()),
+    )
+        .prop_map(|(tag, id, classes)| TestElement {
+            tag,
+            id,
+            classes,
+            children: vec![],
+        });
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        (
+            prop::sample::select(TAGS),
+            prop::option::of(prop::sample::select(IDS)),
+            prop::sample::subsequence(CLASSES, 0..=CLASSES.len()),
+            prop::collection::vec(inner, 0..4),
+        )
+            .prop_map(|(tag, id, classes, children)| TestElement {
+                tag,
+                id,
+                classes,
+                children,
+            })
+    })
+}
+
+fn document_strategy() -> impl Strategy<Value = String> {
+    prop::collection::vec(element_strategy(), 1..4).prop_map(|elements| document_html(&elements))
+}
+
+/// A compound selector from the grammar html_query supports.
+fn compound_selector_strategy() -> impl Strategy<Value = String> {
+    let classes = prop::sample::subsequence(CLASSES, 1..=CLASSES.len());
+    prop_oneof![
+        Just("*".to_string()),
+        prop::sample::select(TAGS).prop_map(str::to_string),
+        prop::sample::select(IDS).prop_map(|id| format!("#{id}")),
+        classes
+            .clone()
+            .prop_map(|classes| format!(".{}", classes.join("."))),
+        (prop::sample::select(TAGS), classes.clone())
+            .prop_map(|(tag, classes)| format!("{tag}.{}", classes.join("."))),
+        (prop::sample::select(IDS), classes.clone())
+            .prop_map(|(id, classes)| format!("#{id}.{}", classes.join("."))),
+        (prop::sample::select(TAGS), prop::sample::select(IDS))
+            .prop_map(|(tag, id)| format!("{tag}#{id}")),
+        (
+            prop::sample::select(TAGS),
+            prop::sample::select(IDS),
+            classes
+        )
+            .prop_map(|(tag, id, classes)| format!("{tag}#{id}.{}", classes.join("."))),
+    ]
+}
+
+fn selector_strategy() -> impl Strategy<Value = String> {
+    let complex = prop::collection::vec(compound_selector_strategy(), 1..4)
+        .prop_map(|compounds| compounds.join(" "));
+    prop::collection::vec(complex, 1..3).prop_map(|complexes| complexes.join(","))
+}
+
+fn data_n(element: &Element) -> String {
+    element
+        .attributes
+        .get("data-n")
+        .cloned()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Elements selected by the reference engine, identified by `data-n`.
+///
+/// The reference engine wraps fragments in an `<html>` element, so the
+/// document is placed in a root container and every selector is scoped to it
+/// to give both engines the same ancestors.
+fn reference_select(html: &str, selector: &str) -> Vec<String> {
+    let document = scraper::Html::parse_fragment(&format!("<div id=\"root\">{html}</div>"));
+    let scoped: Vec<_> = selector
+        .split(',')
+        .map(|complex| format!("#root {complex}"))
+        .collect();
+    let selector = scraper::Selector::parse(&scoped.join(",")).unwrap();
+    document
+        .select(&selector)
+        .filter_map(|element| element.value().attr("data-n").map(str::to_string))
+        .collect()
+}
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+proptest! {
+    #[test]
+    fn parser_never_panics(selector in "\\PC{0,16}") {
+        let dom = Dom::parse("<div id=\"a\" class=\"x\"><span>text</span></div>").unwrap();
+        block_on(async {
+            let _ = select(&dom, &selector).await;
+            let _ = explain(&dom, &selector).await;
+        });
+    }
+
+    #[test]
+    fn parser_never_panics_on_selector_like_input(selector in "[a-z*.#, \u{a0}é0-9]{0,12}") {
+        let dom = Dom::parse("<div id=\"a\" class=\"x\"><span>text</span></div>").unwrap();
+        block_on(async {
+            let _ = select(&dom, &selector).await;
+            let _ = find(select(&dom, "div").await.unwrap()[0], &selector).await;
+            let mut document = Document::from(dom.clone());
+            let _ = document.remove(&selector).await;
+        });
+    }
+
+    #[test]
+    fn select_matches_reference_engine(html in document_strategy(), selector in selector_strategy()) {
+        let dom = Dom::parse(&html).unwrap();
+        let selected: Vec<_> = block_on(select(&dom, &selector))
+            .unwrap()
+            .into_iter()
+            .map(data_n)
+            .collect();
+        prop_assert_eq!(selected, reference_select(&html, &selector));
+    }
+
+    #[test]
+    fn explain_agrees_with_select(html in document_strategy(), selector in selector_strategy()) {
+        let dom = Dom::parse(&html).unwrap();
+        let selected: Vec<_> = block_on(select(&dom, &selector))
+            .unwrap()
+            .into_iter()
+            .map(data_n)
+            .collect();
+        let explained: Vec<_> = block_on(explain(&dom, &selector))
+            .unwrap()
+            .into_iter()
+            .filter(|explanation| explanation.matched())
+            .map(|explanation| data_n(explanation.element))
+            .collect();
+        prop_assert_eq!(selected, explained);
+    }
+}
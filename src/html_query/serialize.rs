@@ -0,0 +1,357 @@
+//! Serializes parsed HTML back to markup.
+//!
+//! `html_parser` keeps attributes in a hash map, so their source order is
+//! lost when parsing and can't be recovered from an [`Element`]. Attributes
+//! are written in a stable order instead, see [`outer_html`], unless the
+//! element comes from a [`super::Document`], which rescans the source for
+//! the order. Comments are written as parsed; parse with [`super::parse`]
+//! to keep their whitespace.
+
+use html_parser::{Element, ElementVariant, Node};
+
+/// Elements that never have content or a closing tag in HTML.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose text content is not escaped.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Elements whose content is whitespace sensitive and never pretty printed.
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea"];
+
+/// The source order of an element's attribute names, along with the same
+/// for each of its child nodes. Text and comment nodes have an empty order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AttributeOrder {
+    pub(crate) names: Vec<String>,
+    pub(crate) children: Vec<AttributeOrder>,
+}
+
+/// Serializes an element, including its own tag, back to HTML.
+///
+/// Attribute order is not preserved: `html_parser` does not keep the source
+/// order of attributes, so `id` and `class` are written first followed by
+/// the remaining attributes sorted by name. This keeps the output stable
+/// between runs. Use [`super::Document::html`] to keep the source order.
+pub fn outer_html(element: &Element) -> String {
+    let mut html = String::new();
+    write_element(&mut html, element, None, None, 0);
+    html
+}
+
+/// Serializes the children of an element back to HTML.
+pub fn inner_html(element: &Element) -> String {
+    let mut html = String::new();
+    for child in &element.children {
+        write_node(&mut html, child, None, None, 0, is_raw_text(element));
+    }
+    html
+}
+
+/// Serializes a single node back to HTML.
+pub fn node_html(node: &Node) -> String {
+    let mut html = String::new();
+    write_node(&mut html, node, None, None, 0, false);
+    html
+}
+
+/// Serializes an element like [`outer_html`], but with each element on its own
+/// line indented by `indent` spaces per level.
+pub fn outer_html_pretty(element: &Element, indent: usize) -> String {
+    let mut html = String::new();
+    write_element(&mut html, element, None, Some(indent), 0);
+    html
+}
+
+/// Serializes a list of top level nodes, such as `Dom::children`, writing
+/// attributes in the order given for each node.
+pub(crate) fn nodes_html(
+    nodes: &[Node],
+    orders: &[AttributeOrder],
+    indent: Option<usize>,
+) -> String {
+    let mut html = String::new();
+    for (index, node) in nodes.iter().enumerate() {
+        write_node(&mut html, node, orders.get(index), indent, 0, false);
+    }
+    html
+}
+
+fn is_void(element: &Element) -> bool {
+    VOID_ELEMENTS.contains(&element.name.as_str())
+}
+
+fn is_raw_text(element: &Element) -> bool {
+    RAW_TEXT_ELEMENTS.contains(&element.name.as_str())
+}
+
+fn write_indent(html: &mut String, indent: Option<usize>, depth: usize) {
+    if let Some(indent) = indent {
+        html.push_str(&" ".repeat(indent * depth));
+    }
+}
+
+fn write_newline(html: &mut String, indent: Option<usize>) {
+    if indent.is_some() {
+        html.push('\n');
+    }
+}
+
+fn write_node(
+    html: &mut String,
+    node: &Node,
+    order: Option<&AttributeOrder>,
+    indent: Option<usize>,
+    depth: usize,
+    raw: bool,
+) {
+    match node {
+        Node::Element(element) => write_element(html, element, order, indent, depth),
+        Node::Text(text) => {
+            write_indent(html, indent, depth);
+            if raw {
+                html.push_str(text);
+            } else {
+                html.push_str(&escape(text, false));
+            }
+            write_newline(html, indent);
+        }
+        Node::Comment(comment) => {
+            write_indent(html, indent, depth);
+            html.push_str("<!--");
+            html.push_str(comment);
+            html.push_str("-->");
+            write_newline(html, indent);
+        }
+    }
+}
+
+/// Writes the start tag without its closing `>`. Attributes named in `order`
+/// come first, then any others with `id` and `class` followed by the rest
+/// sorted by name.
+fn write_start_tag(html: &mut String, element: &Element, order: &[String]) {
+    html.push('<');
+    html.push_str(&element.name);
+
+    let mut attributes: Vec<_> = element.attributes.keys().map(String::as_str).collect();
+    attributes.sort();
+    let stable = element
+        .id
+        .as_ref()
+        .map(|_| "id")
+        .into_iter()
+        .chain((!element.classes.is_empty()).then_some("class"))
+        .chain(attributes);
+    let mut names = Vec::new();
+    for name in order.iter().map(String::as_str).chain(stable) {
+        if !names.contains(&name) && has_attribute(element, name) {
+            names.push(name);
+        }
+    }
+
+    for name in names {
+        let value = match name {
+            "id" => element.id.clone(),
+            "class" => Some(element.classes.join(" ")),
+            _ => element.attributes[name].clone(),
+        };
+        html.push(' ');
+        html.push_str(name);
+        if let Some(value) = value {
+            html.push_str(&format!("=\"{}\"", escape(&value, true)));
+        }
+    }
+}
+
+fn has_attribute(element: &Element, name: &str) -> bool {
+    match name {
+        "id" => element.id.is_some(),
+        "class" => !element.classes.is_empty(),
+        _ => element.attributes.contains_key(name),
+    }
+}
+
+fn write_element(
+    html: &mut String,
+    element: &Element,
+    order: Option<&AttributeOrder>,
+    indent: Option<usize>,
+    depth: usize,
+) {
+    write_indent(html, indent, depth);
+    write_start_tag(html, element, order.map_or(&[], |order| &order.names));
+
+    if is_void(element) {
+        html.push('>');
+        write_newline(html, indent);
+        return;
+    }
+    if element.variant == ElementVariant::Void && element.children.is_empty() {
+        // Self closed foreign elements such as <path/> in inline SVG
+        html.push_str(" />");
+        write_newline(html, indent);
+        return;
+    }
+    html.push('>');
+
+    let raw = is_raw_text(element);
+    let inline = element
+        .children
+        .iter()
+        .all(|child| matches!(child, Node::Text(_)))
+        || PREFORMATTED_ELEMENTS.contains(&element.name.as_str());
+    let child_indent = if inline { None } else { indent };
+    if child_indent.is_some() {
+        html.push('\n');
+    }
+    for (index, child) in element.children.iter().enumerate() {
+        let child_order = order.and_then(|order| order.children.get(index));
+        write_node(html, child, child_order, child_indent, depth + 1, raw);
+    }
+    if child_indent.is_some() {
+        write_indent(html, indent, depth);
+    }
+
+    html.push_str("</");
+    html.push_str(&element.name);
+    html.push('>');
+    write_newline(html, indent);
+}
+
+/// Returns the length of a character reference such as `&amp;` or `&#x27;`
+/// at the start of `text`, if there is one.
+fn character_reference_len(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('&')?;
+    let end = body.find(';')?;
+    let name = &body[..end];
+    let valid = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit())
+    } else {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    if valid {
+        Some(end + 2)
+    } else {
+        None
+    }
+}
+
+/// Escapes text for use in HTML content or a double quoted attribute value.
+///
+/// `html_parser` leaves character references undecoded, so existing
+/// references are passed through rather than escaped a second time.
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        match c {
+            '&' => {
+                if let Some(len) = character_reference_len(&text[index..]) {
+                    escaped.push_str(&text[index..index + len]);
+                    index += len;
+                    continue;
+                }
+                escaped.push_str("&amp;");
+            }
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            c => escaped.push(c),
+        }
+        index += c.len_utf8();
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::{Dom, Node};
+
+    use super::{escape, inner_html, node_html, outer_html, outer_html_pretty};
+    use crate::html_query::parse;
+
+    fn first_element(dom: &Dom) -> &html_parser::Element {
+        match &dom.children[0] {
+            Node::Element(element) => element,
+            node => panic!("Expected element, found {node:?}"),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a & b", false), "a &amp; b");
+        assert_eq!(
+            escape("a &amp; b &#39; &#x27;", false),
+            "a &amp; b &#39; &#x27;"
+        );
+        assert_eq!(escape("a < b > c", false), "a &lt; b &gt; c");
+        assert_eq!(escape("& ;", false), "&amp; ;");
+        assert_eq!(escape(r#"say "hi" <b>"#, true), "say &quot;hi&quot; <b>");
+        assert_eq!(escape("caf\u{e9}\u{a0}", false), "caf\u{e9}&nbsp;");
+    }
+
+    #[test]
+    fn test_outer_and_inner_html() {
+        let dom = Dom::parse(
+            r#"<td class="route name" id="r1" data-sort="2"><a href="/route/?a=1&amp;b=2" target=_blank>Tempus Fugit</a><br/><img src="x.png"></td>"#,
+        )
+        .unwrap();
+        let td = first_element(&dom);
+
+        assert_eq!(
+            outer_html(td),
+            r#"<td id="r1" class="route name" data-sort="2"><a href="/route/?a=1&amp;b=2" target="_blank">Tempus Fugit</a><br><img src="x.png"></td>"#
+        );
+        assert_eq!(
+            inner_html(td),
+            r#"<a href="/route/?a=1&amp;b=2" target="_blank">Tempus Fugit</a><br><img src="x.png">"#
+        );
+        assert_eq!(node_html(&td.children[1]), "<br>");
+    }
+
+    #[test]
+    fn test_raw_text_and_comments() {
+        let html = "<div><!-- ad --><script>if (a<b) x();</script><p>1 > 0</p></div>";
+        assert_eq!(
+            outer_html(first_element(&parse(html).unwrap())),
+            "<div><!-- ad --><script>if (a<b) x();</script><p>1 &gt; 0</p></div>"
+        );
+
+        // html_parser on its own trims comments
+        assert_eq!(
+            outer_html(first_element(&Dom::parse(html).unwrap())),
+            "<div><!--ad--><script>if (a<b) x();</script><p>1 &gt; 0</p></div>"
+        );
+
+        let comments = parse("<!--\n  header\n--><p>a<!---->b<!--  x  --></p><!--x-->").unwrap();
+        assert_eq!(
+            comments.children.iter().map(node_html).collect::<String>(),
+            "<!--\n  header\n--><p>a<!---->b<!--  x  --></p><!--x-->"
+        );
+    }
+
+    #[test]
+    fn test_outer_html_pretty() {
+        let dom = Dom::parse(
+            r#"<ul id="list"><li class="item">Item 1</li><li>Item <b>2</b></li><br></ul>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            outer_html_pretty(first_element(&dom), 2),
+            r#"<ul id="list">
+  <li class="item">Item 1</li>
+  <li>
+    Item
+    <b>2</b>
+  </li>
+  <br>
+</ul>
+"#
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use html_parser::{Element, Node};
+
+/// Named character references commonly found in scraped pages.
+const NAMED_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '\u{2013}'),
+    ("mdash", '\u{2014}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("ldquo", '\u{201c}'),
+    ("rdquo", '\u{201d}'),
+    ("hellip", '\u{2026}'),
+    ("deg", '\u{b0}'),
+    ("times", '\u{d7}'),
+];
+
+/// The text content of an element and its descendants, with character
+/// references decoded and runs of whitespace collapsed to a single space.
+///
+/// `html_parser` trims text nodes, so text from adjacent nodes is separated by
+/// a space, e.g. `23.4km <span>(14.5 miles)</span>` becomes
+/// `23.4km (14.5 miles)`.
+pub fn text(element: &Element) -> String {
+    let mut parts = Vec::new();
+    collect_text(element, &mut parts);
+    let joined = decode_entities(&parts.join(" "));
+    joined.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn collect_text<'a>(element: &'a Element, parts: &mut Vec<&'a str>) {
+    for child in &element.children {
+        match child {
+            Node::Text(text) => parts.push(text),
+            Node::Element(element) => collect_text(element, parts),
+            Node::Comment(_) => {}
+        }
+    }
+}
+
+/// Decodes numeric and common named character references. Unknown references
+/// are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let replacement = rest.find(';').and_then(|end| {
+            let name = &rest[1..end];
+            let character =
+                if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(decimal) = name.strip_prefix('#') {
+                    decimal.parse().ok().and_then(char::from_u32)
+                } else {
+                    NAMED_REFERENCES
+                        .iter()
+                        .find(|(reference, _)| *reference == name)
+                        .map(|(_, character)| *character)
+                };
+            character.map(|character| (character, end + 1))
+        });
+        match replacement {
+            Some((character, len)) => {
+                decoded.push(character);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::{Dom, Node};
+
+    use super::{decode_entities, text};
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("Fish &amp; Chips"), "Fish & Chips");
+        assert_eq!(decode_entities("&#39;&#x2603;&lt;"), "'\u{2603}<");
+        assert_eq!(decode_entities("R&D &unknown; &"), "R&D &unknown; &");
+    }
+
+    #[test]
+    fn test_text() {
+        let dom = Dom::parse(
+            "<td><a href=\"#\">Road to Sky</a><br><span>23.4km  (14.5&nbsp;miles)</span><!-- x --></td>",
+        )
+        .unwrap();
+        let Node::Element(td) = &dom.children[0] else {
+            panic!("Expected element");
+        };
+        assert_eq!(text(td), "Road to Sky 23.4km (14.5 miles)");
+    }
+}
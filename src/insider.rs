@@ -1,7 +1,51 @@
+//! Scrapers for the route data published on [Zwift Insider](https://zwiftinsider.com).
+
 use anyhow::Result;
+use log::debug;
+
+mod page;
+mod portal;
+mod route_details;
+mod routes;
+mod schedule;
+mod segments;
+mod workouts;
+
+pub use portal::{
+    featured_climbs, parse_portal_climbs, parse_portal_schedule, FeaturedClimb, PortalClimb,
+    PortalSchedule,
+};
+pub use route_details::{
+    fetch_route_details, parse_elevation_profile, parse_route_details, RouteDetails,
+};
+pub(crate) use routes::normalize_name;
+pub use routes::{
+    find_route, merge_routes, parse_routes, parse_running_routes, Route, RouteRestriction, Sport,
+};
+pub use schedule::{parse_world_schedule, WorldSchedule};
+pub use segments::{parse_segments, Direction, Segment, SegmentIndex, SegmentType};
+pub use workouts::{
+    parse_workout_collection, parse_workout_collections, WorkoutCollection, WorkoutListing,
+};
+
+/// The Zwift Insider page listing every Zwift route.
+pub const ROUTES_URL: &str = "https://zwiftinsider.com/routes/";
+
+/// The Zwift Insider page listing the routes that can be run.
+pub const RUNNING_ROUTES_URL: &str = "https://zwiftinsider.com/running-routes/";
+
+/// The Zwift Insider Climb Portal page, listing the portal climbs and the
+/// schedule of featured climbs.
+pub const CLIMB_PORTAL_URL: &str = "https://zwiftinsider.com/climb-portal/";
 
-struct ZwitInsider {}
+/// The Zwift Insider guest world calendar.
+pub const GUEST_WORLD_CALENDAR_URL: &str = "https://zwiftinsider.com/schedule/";
 
-impl ZwitInsider {
+/// The Zwift Insider page linking to its workout collections.
+pub const WORKOUTS_URL: &str = "https://zwiftinsider.com/workouts/";
 
-}
\ No newline at end of file
+/// Downloads a web page as text.
+pub async fn download_webpage(url: &str) -> Result<String> {
+    debug!("Downloading web page {url}...");
+    Ok(reqwest::get(url).await?.text().await?)
+}
@@ -0,0 +1,208 @@
+//! Helpers for reading the article structure of Zwift Insider pages, which
+//! are WordPress posts made of headings followed by paragraphs and lists.
+
+use html_parser::{Dom, Element, Node};
+
+use crate::html_query;
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+const CONTAINERS: &[&str] = &["div", "section", "article", "blockquote"];
+
+/// A line of text, e.g. a `<br>` separated line of a paragraph or a list
+/// item, along with the links it contains.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Line {
+    pub text: String,
+    pub links: Vec<String>,
+}
+
+impl Line {
+    /// Splits `Label: value` lines at the first colon.
+    pub fn label_value(&self) -> Option<(&str, &str)> {
+        let (label, value) = self.text.split_once(':')?;
+        Some((label.trim(), value.trim()))
+    }
+}
+
+/// The content following a heading, up to the next heading.
+#[derive(Debug)]
+pub(crate) struct Section<'a> {
+    pub heading: String,
+    pub nodes: Vec<&'a Node>,
+}
+
+impl<'a> Section<'a> {
+    pub fn lines(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for node in &self.nodes {
+            collect_lines(node, &mut lines);
+        }
+        lines.retain(|line| !line.text.is_empty());
+        lines
+    }
+}
+
+fn is_heading(element: &Element) -> bool {
+    HEADINGS.contains(&element.name.as_str())
+}
+
+/// The post title, from `h1.entry-title` or failing that the first `h1`.
+pub(crate) async fn title(dom: &Dom) -> Option<String> {
+    for selector in ["h1.entry-title", "h1"] {
+        if let Some(heading) = html_query::select(dom, selector).await.ok()?.first() {
+            return Some(html_query::text(heading));
+        }
+    }
+    None
+}
+
+/// Splits the post content into sections by heading. Content before the first
+/// heading is returned with an empty heading.
+pub(crate) async fn sections(dom: &Dom) -> Vec<Section<'_>> {
+    let content = match html_query::select(dom, ".entry-content").await {
+        Ok(elements) if !elements.is_empty() => Some(elements[0].children.as_slice()),
+        _ => find_heading_container(&dom.children),
+    };
+
+    let mut sections = vec![Section {
+        heading: String::new(),
+        nodes: Vec::new(),
+    }];
+    for node in content.unwrap_or_default() {
+        match node {
+            Node::Element(element) if is_heading(element) => sections.push(Section {
+                heading: html_query::text(element),
+                nodes: Vec::new(),
+            }),
+            Node::Comment(_) => {}
+            node => sections.last_mut().unwrap().nodes.push(node),
+        }
+    }
+    sections
+}
+
+/// Finds the first sections whose heading contains `heading`, ignoring case.
+pub(crate) fn find_section<'a, 'b>(
+    sections: &'b [Section<'a>],
+    heading: &str,
+) -> Option<&'b Section<'a>> {
+    let heading = heading.to_lowercase();
+    sections
+        .iter()
+        .find(|section| section.heading.to_lowercase().contains(&heading))
+}
+
+/// Finds the children of the element with the most headings directly inside
+/// it, which is the post content on pages without `.entry-content`.
+fn find_heading_container(nodes: &[Node]) -> Option<&[Node]> {
+    fn visit<'a>(nodes: &'a [Node], best: &mut Option<(usize, &'a [Node])>) {
+        let headings = nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Element(element) if is_heading(element)))
+            .count();
+        if headings > best.map_or(0, |(count, _)| count) {
+            *best = Some((headings, nodes));
+        }
+        for node in nodes {
+            if let Node::Element(element) = node {
+                visit(&element.children, best);
+            }
+        }
+    }
+
+    let mut best = None;
+    visit(nodes, &mut best);
+    best.map(|(_, nodes)| nodes)
+}
+
+fn collect_lines(node: &Node, lines: &mut Vec<Line>) {
+    match node {
+        Node::Text(text) => lines.push(Line {
+            text: html_query::decode_entities(text)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+            links: Vec::new(),
+        }),
+        // Chart data and styles embedded in the post aren't content
+        Node::Element(element) if element.name == "script" || element.name == "style" => {}
+        Node::Element(element) if element.name == "ul" || element.name == "ol" => {
+            for child in &element.children {
+                if let Node::Element(item) = child {
+                    lines.push(line(item));
+                }
+            }
+        }
+        Node::Element(element) if CONTAINERS.contains(&element.name.as_str()) => {
+            for child in &element.children {
+                collect_lines(child, lines);
+            }
+        }
+        Node::Element(element) => {
+            // Split paragraphs and other inline content at <br>
+            let mut current = Element::default();
+            for child in &element.children {
+                match child {
+                    Node::Element(br) if br.name == "br" => {
+                        lines.push(line(&current));
+                        current.children.clear();
+                    }
+                    child => current.children.push(child.clone()),
+                }
+            }
+            lines.push(line(&current));
+        }
+        Node::Comment(_) => {}
+    }
+}
+
+fn line(element: &Element) -> Line {
+    let mut links = Vec::new();
+    collect_links(element, &mut links);
+    Line {
+        text: html_query::text(element),
+        links,
+    }
+}
+
+fn collect_links(element: &Element, links: &mut Vec<String>) {
+    if element.name == "a" {
+        if let Some(Some(href)) = element.attributes.get("href") {
+            links.push(html_query::decode_entities(href));
+        }
+    }
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            collect_links(child, links);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::Dom;
+
+    use super::{find_section, sections, title};
+
+    #[tokio::test]
+    async fn test_sections() {
+        let dom = Dom::parse(
+            r#"<div><h1>Post</h1><div class="body"><p>Intro</p><h3>Details</h3>
+            <p>Start: Here<br>Link: <a href="/a?x=1&amp;y=2">there</a></p>
+            <ul><li>One</li><li>Two</li></ul><h3>Notes</h3><p>Last</p></div></div>"#,
+        )
+        .unwrap();
+        assert_eq!(title(&dom).await.as_deref(), Some("Post"));
+
+        let sections = sections(&dom).await;
+        let headings: Vec<_> = sections.iter().map(|s| s.heading.as_str()).collect();
+        assert_eq!(headings, vec!["", "Details", "Notes"]);
+
+        let details = find_section(&sections, "details").unwrap().lines();
+        let texts: Vec<_> = details.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["Start: Here", "Link: there", "One", "Two"]);
+        assert_eq!(details[1].label_value(), Some(("Link", "there")));
+        assert_eq!(details[1].links, vec!["/a?x=1&y=2"]);
+        assert!(find_section(&sections, "strategy").is_none());
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, NaiveDate};
+use html_parser::{Dom, Element};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{routes::normalize_name, segments::parse_grade};
+use crate::{
+    html_query,
+    units::{Distance, Elevation},
+    world::World,
+};
+
+/// A real world climb that can be ridden through the Climb Portal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortalClimb {
+    pub name: String,
+    pub length: Distance,
+    pub elevation_gain: Elevation,
+    /// Average grade in percent
+    pub average_grade: Option<f64>,
+    /// Maximum grade in percent
+    pub max_grade: Option<f64>,
+    pub country: Option<String>,
+}
+
+impl PortalClimb {
+    /// Whether `name` refers to this climb, ignoring case and punctuation.
+    pub fn matches_name(&self, name: &str) -> bool {
+        normalize_name(&self.name) == normalize_name(name)
+    }
+}
+
+/// A climb featured in a world's portal on a day of the schedule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeaturedClimb {
+    pub date: NaiveDate,
+    pub world: World,
+    pub climb: String,
+}
+
+/// The climbs featured in each world's portal by day.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PortalSchedule {
+    days: BTreeMap<NaiveDate, Vec<(World, String)>>,
+}
+
+impl PortalSchedule {
+    pub fn insert(&mut self, date: NaiveDate, world: World, climb: String) {
+        let climbs = self.days.entry(date).or_default();
+        climbs.retain(|(existing, _)| *existing != world);
+        climbs.push((world, climb));
+    }
+
+    /// The climbs featured from `start` to `end` inclusive, by date.
+    pub fn featured_between(&self, start: NaiveDate, end: NaiveDate) -> Vec<FeaturedClimb> {
+        self.days
+            .range(start..=end)
+            .flat_map(|(date, climbs)| {
+                climbs.iter().map(|(world, climb)| FeaturedClimb {
+                    date: *date,
+                    world: *world,
+                    climb: climb.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The climbs featured in the Monday to Sunday week containing `date`.
+    pub fn featured_in_week(&self, date: NaiveDate) -> Vec<FeaturedClimb> {
+        let monday = date - Days::new(date.weekday().num_days_from_monday().into());
+        self.featured_between(monday, monday + Days::new(6))
+    }
+}
+
+/// Looks up the featured climbs in the catalogue, once each. Climbs missing
+/// from the catalogue are skipped.
+pub fn featured_climbs<'a>(
+    featured: &[FeaturedClimb],
+    climbs: &'a [PortalClimb],
+) -> Vec<&'a PortalClimb> {
+    let mut found: Vec<&PortalClimb> = Vec::new();
+    for featured in featured {
+        match climbs
+            .iter()
+            .find(|climb| climb.matches_name(&featured.climb))
+        {
+            Some(climb) if !found.iter().any(|existing| std::ptr::eq(*existing, climb)) => {
+                found.push(climb)
+            }
+            Some(_) => {}
+            None => warn!("\"{}\" isn't in the portal climbs", featured.climb),
+        }
+    }
+    found
+}
+
+/// Climbs table columns, identified from the table's header row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClimbColumn {
+    Name,
+    Country,
+    Length,
+    ElevationGain,
+    AverageGrade,
+    MaxGrade,
+}
+
+fn climb_column_for_header(header: &str) -> Option<ClimbColumn> {
+    let header = header.to_lowercase();
+    if header.contains("max") {
+        Some(ClimbColumn::MaxGrade)
+    } else if header.contains("grade") || header.contains("gradient") {
+        Some(ClimbColumn::AverageGrade)
+    } else if header.contains("climb") || header == "name" {
+        Some(ClimbColumn::Name)
+    } else if header.contains("country") || header.contains("location") {
+        Some(ClimbColumn::Country)
+    } else if header.contains("length") || header.contains("distance") {
+        Some(ClimbColumn::Length)
+    } else if header.contains("elev") || header.contains("gain") {
+        Some(ClimbColumn::ElevationGain)
+    } else {
+        None
+    }
+}
+
+/// The header cells of a table's first header row and the rows after it.
+async fn table_header(table: &Element) -> Result<Option<(Vec<String>, Vec<&Element>)>> {
+    let rows = html_query::find(table, "tr").await?;
+    for (index, row) in rows.iter().enumerate() {
+        let headers = html_query::find(row, "th").await?;
+        if !headers.is_empty() {
+            let headers = headers.iter().map(|th| html_query::text(th)).collect();
+            return Ok(Some((headers, rows[index + 1..].to_vec())));
+        }
+    }
+    Ok(None)
+}
+
+async fn parse_climb_row(
+    row: &Element,
+    columns: &[Option<ClimbColumn>],
+) -> Result<Option<PortalClimb>> {
+    let cells = html_query::find(row, "td").await?;
+    if cells.len() != columns.len() {
+        debug!(
+            "Skipping row with {} cells, expected {}",
+            cells.len(),
+            columns.len()
+        );
+        return Ok(None);
+    }
+
+    let mut name = String::new();
+    let mut length = None;
+    let mut climb = PortalClimb {
+        name: String::new(),
+        length: Distance::ZERO,
+        elevation_gain: Elevation::ZERO,
+        average_grade: None,
+        max_grade: None,
+        country: None,
+    };
+    for (cell, column) in cells.iter().zip(columns) {
+        let text = html_query::text(cell);
+        match column {
+            Some(ClimbColumn::Name) => name = text,
+            Some(ClimbColumn::Country) => climb.country = Some(text).filter(|c| !c.is_empty()),
+            Some(ClimbColumn::Length) => length = text.parse().ok(),
+            Some(ClimbColumn::ElevationGain) => {
+                climb.elevation_gain = text.parse().unwrap_or_default()
+            }
+            Some(ClimbColumn::AverageGrade) => climb.average_grade = parse_grade(&text),
+            Some(ClimbColumn::MaxGrade) => climb.max_grade = parse_grade(&text),
+            None => {}
+        }
+    }
+
+    match length {
+        Some(length) if !name.is_empty() => Ok(Some(PortalClimb {
+            name,
+            length,
+            ..climb
+        })),
+        _ => {
+            debug!("Skipping row without a climb name and length: \"{name}\"");
+            Ok(None)
+        }
+    }
+}
+
+/// Parses the table(s) of Climb Portal climbs.
+pub async fn parse_portal_climbs(dom: &Dom) -> Result<Vec<PortalClimb>> {
+    let mut climbs = Vec::new();
+    for table in html_query::select(dom, "table").await? {
+        let Some((headers, rows)) = table_header(table).await? else {
+            continue;
+        };
+        let columns: Vec<_> = headers
+            .iter()
+            .map(|header| climb_column_for_header(header))
+            .collect();
+        if !columns.contains(&Some(ClimbColumn::Name))
+            || !columns.contains(&Some(ClimbColumn::Length))
+        {
+            debug!("Skipping table with headers {headers:?}");
+            continue;
+        }
+        for row in rows {
+            if let Some(climb) = parse_climb_row(row, &columns).await? {
+                climbs.push(climb);
+            }
+        }
+    }
+
+    if climbs.is_empty() {
+        Err(anyhow!("No portal climbs table found"))
+    } else {
+        Ok(climbs)
+    }
+}
+
+/// Parses dates such as "Monday, October 19, 2026", "Oct 19th, 2026" or
+/// "2026-10-19".
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    // Drop ordinal suffixes, e.g. 19th
+    let mut cleaned = String::new();
+    let mut previous = ' ';
+    let mut chars = text.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if previous.is_ascii_digit() && c.is_ascii_alphabetic() {
+            let suffix: String = [c].into_iter().chain(chars.peek().copied()).collect();
+            if ["st", "nd", "rd", "th"].contains(&suffix.to_lowercase().as_str()) {
+                chars.next();
+                previous = c;
+                continue;
+            }
+        }
+        cleaned.push(c);
+        previous = c;
+    }
+
+    [
+        "%Y-%m-%d",
+        "%A, %B %d, %Y",
+        "%B %d, %Y",
+        "%b %d, %Y",
+        "%d %B %Y",
+        "%a %d %b %Y",
+    ]
+    .iter()
+    .find_map(|format| NaiveDate::parse_from_str(&cleaned, format).ok())
+}
+
+/// Schedule table columns. Schedules either have a column per portal world,
+/// e.g. "Watopia Portal", or a world column and a climb column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScheduleColumn {
+    Date,
+    World,
+    Climb,
+    Portal(World),
+}
+
+fn schedule_column_for_header(header: &str) -> Option<ScheduleColumn> {
+    let lower = header.to_lowercase();
+    let portal_world = lower
+        .split_whitespace()
+        .filter(|word| *word != "portal" && *word != "climb")
+        .collect::<Vec<_>>()
+        .join(" ");
+    if lower.contains("date") || lower == "day" {
+        Some(ScheduleColumn::Date)
+    } else if let Ok(world) = portal_world.parse() {
+        Some(ScheduleColumn::Portal(world))
+    } else if lower.contains("world") || lower.contains("portal") {
+        Some(ScheduleColumn::World)
+    } else if lower.contains("climb") {
+        Some(ScheduleColumn::Climb)
+    } else {
+        None
+    }
+}
+
+/// Parses the Climb Portal schedule table(s).
+pub async fn parse_portal_schedule(dom: &Dom) -> Result<PortalSchedule> {
+    let mut schedule = PortalSchedule::default();
+    for table in html_query::select(dom, "table").await? {
+        let Some((headers, rows)) = table_header(table).await? else {
+            continue;
+        };
+        let columns: Vec<_> = headers
+            .iter()
+            .map(|header| schedule_column_for_header(header))
+            .collect();
+        let has_climbs = columns.iter().any(|column| {
+            matches!(
+                column,
+                Some(ScheduleColumn::Climb) | Some(ScheduleColumn::Portal(_))
+            )
+        });
+        if !columns.contains(&Some(ScheduleColumn::Date)) || !has_climbs {
+            debug!("Skipping table with headers {headers:?}");
+            continue;
+        }
+
+        for row in rows {
+            let cells = html_query::find(row, "td").await?;
+            if cells.len() != columns.len() {
+                continue;
+            }
+            let texts: Vec<_> = cells.iter().map(|cell| html_query::text(cell)).collect();
+            let mut date = None;
+            let mut world = None;
+            let mut climb = None;
+            for (text, column) in texts.iter().zip(&columns) {
+                match column {
+                    Some(ScheduleColumn::Date) => date = parse_date(text),
+                    Some(ScheduleColumn::World) => world = text.parse::<World>().ok(),
+                    Some(ScheduleColumn::Climb) => climb = Some(text.clone()),
+                    _ => {}
+                }
+            }
+            let Some(date) = date else {
+                debug!("Skipping schedule row without a date: {texts:?}");
+                continue;
+            };
+            for (text, column) in texts.iter().zip(&columns) {
+                if let Some(ScheduleColumn::Portal(world)) = column {
+                    if !text.is_empty() && text != "-" {
+                        schedule.insert(date, *world, text.clone());
+                    }
+                }
+            }
+            if let (Some(world), Some(climb)) = (world, climb) {
+                schedule.insert(date, world, climb);
+            }
+        }
+    }
+
+    if schedule.days.is_empty() {
+        Err(anyhow!("No portal schedule found"))
+    } else {
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use html_parser::Dom;
+
+    use super::{featured_climbs, parse_date, parse_portal_climbs, parse_portal_schedule};
+    use crate::{
+        units::{Distance, Elevation},
+        world::World,
+    };
+
+    static CLIMB_PORTAL_HTML: &str = include_str!("../../fixtures/insider/climb-portal.html");
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("Monday, October 19, 2026"), Some(date(10, 19)));
+        assert_eq!(parse_date("Oct 19th, 2026"), Some(date(10, 19)));
+        assert_eq!(parse_date("2026-10-01"), Some(date(10, 1)));
+        assert_eq!(parse_date("1st November 2026"), Some(date(11, 1)));
+        assert_eq!(parse_date("Soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_portal_climbs() {
+        let dom = Dom::parse(CLIMB_PORTAL_HTML).unwrap();
+        let climbs = parse_portal_climbs(&dom).await.unwrap();
+        assert_eq!(climbs.len(), 7);
+        let tourmalet = &climbs[0];
+        assert_eq!(tourmalet.name, "Col du Tourmalet");
+        assert_eq!(tourmalet.length, Distance::from_km(17.1));
+        assert_eq!(tourmalet.elevation_gain, Elevation::from_meters(1268.0));
+        assert_eq!(tourmalet.average_grade, Some(7.4));
+        assert_eq!(tourmalet.max_grade, Some(10.2));
+        assert_eq!(tourmalet.country.as_deref(), Some("France"));
+        assert!(climbs[1].matches_name("Alpe d'Huez"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_portal_schedule() {
+        let dom = Dom::parse(CLIMB_PORTAL_HTML).unwrap();
+        let schedule = parse_portal_schedule(&dom).await.unwrap();
+        let climbs = parse_portal_climbs(&dom).await.unwrap();
+
+        let today = schedule.featured_between(date(10, 18), date(10, 18));
+        assert_eq!(today.len(), 2);
+        assert_eq!(today[0].world, World::Watopia);
+        assert_eq!(today[0].climb, "Old La Honda");
+        assert_eq!(today[1].world, World::France);
+        assert_eq!(today[1].climb, "Mont Ventoux");
+
+        // Sunday the 18th is in the week starting Monday the 12th
+        let week = schedule.featured_in_week(date(10, 18));
+        assert_eq!(week.len(), 14);
+        assert_eq!(week[0].date, date(10, 12));
+        assert_eq!(week[13].date, date(10, 18));
+
+        let featured = featured_climbs(&week, &climbs);
+        assert_eq!(featured.len(), 7);
+        assert_eq!(featured[0].name, "Old La Honda");
+        assert_eq!(featured[1].name, "Col du Tourmalet");
+
+        // The schedule ends on November 1st
+        assert_eq!(schedule.featured_in_week(date(11, 4)).len(), 0);
+        assert_eq!(schedule.featured_in_week(date(10, 28)).len(), 2 * 7);
+    }
+}
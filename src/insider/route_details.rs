@@ -0,0 +1,364 @@
+use anyhow::{anyhow, Result};
+use html_parser::{Dom, Node};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    download_webpage,
+    page::{self, Line},
+    segments::normalize_segment_name,
+    Route, ROUTES_URL,
+};
+use crate::{
+    html_query,
+    profile::ElevationProfile,
+    units::{Distance, Elevation},
+};
+
+/// The data on a route's Zwift Insider detail page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteDetails {
+    pub name: String,
+    pub start_location: Option<String>,
+    pub distance: Option<Distance>,
+    pub elevation: Option<Elevation>,
+    pub lead_in_distance: Option<Distance>,
+    pub laps: Option<u32>,
+    pub strava_segment_url: Option<String>,
+    pub koms: Vec<String>,
+    pub sprints: Vec<String>,
+    /// The "Route Strategy" notes, one paragraph per line
+    pub strategy: Option<String>,
+    /// Every label and value of the "Route Details" block, including those
+    /// not parsed into the fields above
+    pub details: Vec<(String, String)>,
+    /// The elevation profile, if the page embeds one
+    pub profile: Option<ElevationProfile>,
+}
+
+/// Splits a list of segment names such as `Epic KOM, Watopia Sprint`, where
+/// `None` or `-` means an empty list.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty() && *item != "-" && !item.eq_ignore_ascii_case("none"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Adds a segment unless it is already listed, allowing for the same segment
+/// being named "Alpe du Zwift" in one place and "Alpe du Zwift KOM" in another.
+/// Forward and reverse segments are different segments.
+fn push_unique(list: &mut Vec<String>, item: String) {
+    let key = normalize_segment_name(&item);
+    let listed = list
+        .iter()
+        .any(|existing| normalize_segment_name(existing) == key);
+    if !listed && !item.is_empty() {
+        list.push(item);
+    }
+}
+
+/// Strips notes such as `(12.2km, 8.5% average)` from a segment list item.
+fn segment_name(text: &str) -> String {
+    text.split('(')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Returns the JSON array starting at the beginning of `text`, matching
+/// brackets so nested arrays are included.
+fn json_array(text: &str) -> Option<&str> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[..=index]);
+                }
+            }
+            _ if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds a profile in script source, taking the first array after a mention
+/// of "elevation" that parses as one, e.g.
+/// `var elevationProfile = [[0, 10], [0.5, 12.5]];`.
+fn script_profile(script: &str) -> Option<ElevationProfile> {
+    // ASCII lowercasing keeps byte offsets the same as in `script`
+    let lower = script.to_ascii_lowercase();
+    lower.match_indices("elevation").find_map(|(index, _)| {
+        let rest = &script[index..];
+        let start = rest.find('[')?;
+        ElevationProfile::from_json(json_array(&rest[start..])?).ok()
+    })
+}
+
+/// Finds the elevation profile embedded in a page, either as JSON in a
+/// `data-elevation-profile` attribute or in the source of a chart script.
+pub async fn parse_elevation_profile(dom: &Dom) -> Option<ElevationProfile> {
+    let elements = html_query::select(dom, "*").await.ok()?;
+    let attribute_profile = elements.iter().find_map(|element| {
+        let json = element
+            .attributes
+            .get("data-elevation-profile")?
+            .as_deref()?;
+        ElevationProfile::from_json(&html_query::decode_entities(json))
+            .map_err(|error| debug!("Ignoring elevation profile: {error:#}"))
+            .ok()
+    });
+    attribute_profile.or_else(|| {
+        elements
+            .iter()
+            .filter(|element| element.name == "script")
+            .flat_map(|element| &element.children)
+            .find_map(|child| match child {
+                Node::Text(script) => script_profile(script),
+                _ => None,
+            })
+    })
+}
+
+fn apply_detail(details: &mut RouteDetails, line: &Line, label: &str, value: &str) {
+    let label = label.to_lowercase();
+    if label.contains("strava") {
+        details.strava_segment_url = line
+            .links
+            .iter()
+            .find(|link| link.contains("strava.com"))
+            .cloned();
+    } else if label.contains("start") {
+        details.start_location = Some(value.to_string()).filter(|value| !value.is_empty());
+    } else if label.contains("lead") {
+        details.lead_in_distance = value.parse().ok();
+    } else if label.contains("lap") {
+        details.laps = value.parse().ok();
+    } else if label.contains("kom") || label.contains("qom") {
+        for kom in parse_list(value) {
+            push_unique(&mut details.koms, kom);
+        }
+    } else if label.contains("sprint") {
+        for sprint in parse_list(value) {
+            push_unique(&mut details.sprints, sprint);
+        }
+    } else if label.contains("elev") || label.contains("climb") {
+        details.elevation = value.parse().ok();
+    } else if label.contains("distance") || label.contains("length") {
+        details.distance = value.parse().ok();
+    }
+}
+
+/// Parses a route's Zwift Insider detail page.
+///
+/// The page is read by section heading: "Route Details" holds `Label: value`
+/// lines, "Segments" lists the KOMs and sprints and "Strategy" or "Tips" the
+/// notes on riding the route. The elevation profile is read from the chart
+/// data, wherever it is on the page.
+pub async fn parse_route_details(dom: &Dom) -> Result<RouteDetails> {
+    let name = page::title(dom)
+        .await
+        .ok_or_else(|| anyhow!("Route page has no title"))?;
+    let sections = page::sections(dom).await;
+
+    let mut details = RouteDetails {
+        name,
+        start_location: None,
+        distance: None,
+        elevation: None,
+        lead_in_distance: None,
+        laps: None,
+        strava_segment_url: None,
+        koms: Vec::new(),
+        sprints: Vec::new(),
+        strategy: None,
+        details: Vec::new(),
+        profile: parse_elevation_profile(dom).await,
+    };
+
+    let details_section = page::find_section(&sections, "route details")
+        .ok_or_else(|| anyhow!("No \"Route Details\" section on \"{}\"", details.name))?;
+    for line in details_section.lines() {
+        match line.label_value() {
+            Some((label, value)) => {
+                apply_detail(&mut details, &line, label, value);
+                details.details.push((label.to_string(), value.to_string()));
+            }
+            None => debug!("Ignoring route details line \"{}\"", line.text),
+        }
+    }
+
+    if let Some(segments) = page::find_section(&sections, "segments") {
+        for line in segments.lines() {
+            let text = line.text.to_lowercase();
+            if text.contains("sprint") {
+                push_unique(&mut details.sprints, segment_name(&line.text));
+            } else if text.contains("kom") || text.contains("qom") {
+                push_unique(&mut details.koms, segment_name(&line.text));
+            }
+        }
+    }
+
+    details.strategy = page::find_section(&sections, "strategy")
+        .or_else(|| page::find_section(&sections, "tips"))
+        .map(|section| {
+            section
+                .lines()
+                .into_iter()
+                .map(|line| line.text)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|strategy| !strategy.is_empty());
+
+    Ok(details)
+}
+
+/// Downloads and parses the detail page of each route that links to one.
+///
+/// Pages are fetched one at a time. Each result is paired with the route row
+/// it came from, so a failure on one page doesn't lose the others.
+pub async fn fetch_route_details(routes: &[Route]) -> Vec<(&Route, Result<RouteDetails>)> {
+    let mut results = Vec::new();
+    for route in routes {
+        let Some(href) = &route.detail_url else {
+            warn!("Route \"{}\" has no detail page", route.name);
+            continue;
+        };
+        let details = async {
+            let url = reqwest::Url::parse(ROUTES_URL)?.join(href)?;
+            let web_page = download_webpage(url.as_str()).await?;
+            parse_route_details(&Dom::parse(&web_page)?).await
+        }
+        .await;
+        results.push((route, details));
+    }
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::Dom;
+
+    use super::{
+        json_array, parse_elevation_profile, parse_list, parse_route_details, script_profile,
+    };
+    use crate::units::{Distance, Elevation};
+
+    static ROAD_TO_SKY_HTML: &str = include_str!("../../fixtures/insider/route-road-to-sky.html");
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list("Epic KOM, Radio Tower"),
+            vec!["Epic KOM", "Radio Tower"]
+        );
+        assert!(parse_list("None").is_empty());
+        assert!(parse_list("-").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_parse_route_details() {
+        let dom = Dom::parse(ROAD_TO_SKY_HTML).unwrap();
+        let details = parse_route_details(&dom).await.unwrap();
+        assert_eq!(details.name, "Road to Sky");
+        assert_eq!(details.start_location.as_deref(), Some("Watopia Esplanade"));
+        assert_eq!(details.distance, Some(Distance::from_km(17.5)));
+        assert_eq!(details.elevation, Some(Elevation::from_meters(1036.0)));
+        assert_eq!(details.lead_in_distance, Some(Distance::from_km(0.9)));
+        assert_eq!(details.laps, Some(1));
+        assert_eq!(
+            details.strava_segment_url.as_deref(),
+            Some("https://www.strava.com/segments/12744502")
+        );
+        assert_eq!(details.koms, vec!["Alpe du Zwift"]);
+        assert_eq!(details.sprints, vec!["Watopia Sprint Forward"]);
+        assert_eq!(
+            details.strategy.as_deref(),
+            Some(
+                "Pace yourself on the first few switchbacks.\n\
+                 The grade eases slightly after turn 11, so settle in and ride steady to the top."
+            )
+        );
+        assert_eq!(details.details.len(), 8);
+        assert_eq!(
+            details.details[6],
+            ("Sprints".to_string(), "None".to_string())
+        );
+        let profile = details.profile.unwrap();
+        assert_eq!(profile.samples().len(), 8);
+        assert_eq!(profile.length(), Distance::from_km(17.5));
+        assert_eq!(profile.elevation_gain(), Elevation::from_meters(1036.0));
+    }
+
+    #[tokio::test]
+    async fn test_parse_reverse_segments() {
+        let html = r#"<html><body>
+            <h1 class="entry-title">Volcano Climb</h1>
+            <h3>Route Details</h3>
+            <p>Distance: 20km<br>
+            KOMs: Epic KOM<br>
+            Sprints: Watopia Sprint, Watopia Sprint Reverse</p>
+            <h3>Segments on this Route</h3>
+            <ul>
+              <li>Epic KOM Reverse (9.4km, 3.6% average)</li>
+              <li>Epic KOM Forward</li>
+              <li>Watopia Sprint Rev</li>
+            </ul>
+        </body></html>"#;
+        let dom = Dom::parse(html).unwrap();
+        let details = parse_route_details(&dom).await.unwrap();
+        assert_eq!(details.koms, vec!["Epic KOM", "Epic KOM Reverse"]);
+        assert_eq!(
+            details.sprints,
+            vec!["Watopia Sprint", "Watopia Sprint Reverse"]
+        );
+    }
+
+    #[test]
+    fn test_json_array() {
+        assert_eq!(json_array("[[0, 1], [2, 3]];"), Some("[[0, 1], [2, 3]]"));
+        assert_eq!(json_array("[[0, 1]"), None);
+        assert_eq!(json_array("x = [1]"), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_elevation_profile() {
+        let dom =
+            Dom::parse(r#"<div class="chart" data-elevation-profile="[[0, 5], [1.2, 25]]"></div>"#)
+                .unwrap();
+        let profile = parse_elevation_profile(&dom).await.unwrap();
+        assert_eq!(profile.length(), Distance::from_km(1.2));
+        assert_eq!(profile.elevation_gain(), Elevation::from_meters(20.0));
+
+        let dom = Dom::parse(
+            "<script>var colors = ['red'];\n\
+             var elevation = {units: 'm'};\n\
+             var elevationData = [{\"distance\": 0, \"elevation\": 5}, {\"distance\": 2, \"elevation\": 1}];</script>",
+        )
+        .unwrap();
+        let profile = parse_elevation_profile(&dom).await.unwrap();
+        assert_eq!(profile.length(), Distance::from_km(2.0));
+
+        // Non-ASCII text before the profile changes length when lowercased
+        let script = format!("// {}\nvar elevation = [[0, 5], [1, 6]];", "İ".repeat(20));
+        let profile = script_profile(&script).unwrap();
+        assert_eq!(profile.length(), Distance::from_km(1.0));
+
+        let dom = Dom::parse("<p>Elevation: [flat]</p><script>var x = [1, 2];</script>").unwrap();
+        assert_eq!(parse_elevation_profile(&dom).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_route_details_without_details() {
+        let dom = Dom::parse("<h1>Some Post</h1><p>No route here</p>").unwrap();
+        assert!(parse_route_details(&dom).await.is_err());
+    }
+}
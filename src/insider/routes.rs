@@ -0,0 +1,642 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use html_parser::{Dom, Element, Node};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    html_query,
+    units::{Distance, Elevation},
+    world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Sport {
+    Cycling,
+    Running,
+}
+
+impl FromStr for Sport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_sport(s).ok_or_else(|| anyhow!("Unknown sport \"{s}\", expected cycling or running"))
+    }
+}
+
+impl fmt::Display for Sport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sport::Cycling => write!(f, "Cycling"),
+            Sport::Running => write!(f, "Running"),
+        }
+    }
+}
+
+/// A limit on who can ride a route, shown as text, icons or CSS classes in
+/// the routes table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RouteRestriction {
+    EventOnly,
+    RunOnly,
+    MinimumLevel(u32),
+    /// A restriction noted in the table that isn't otherwise recognized
+    Other(String),
+}
+
+impl RouteRestriction {
+    /// Recognizes restrictions in text such as "Event Only", "Level 6+" or the
+    /// `run-only` class. Words are matched whole, so "Events calendar" is not
+    /// a restriction.
+    fn from_text(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        let words: Vec<_> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let only = |sport: &[&str]| {
+            words
+                .windows(2)
+                .any(|pair| sport.contains(&pair[0]) && pair[1] == "only")
+        };
+        if only(&["event", "events"]) {
+            Some(RouteRestriction::EventOnly)
+        } else if only(&["run", "running"]) {
+            Some(RouteRestriction::RunOnly)
+        } else {
+            words.windows(2).find_map(|pair| match pair[0] {
+                "level" | "lvl" => pair[1].parse().ok().map(RouteRestriction::MinimumLevel),
+                _ => None,
+            })
+        }
+    }
+}
+
+impl fmt::Display for RouteRestriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteRestriction::EventOnly => write!(f, "Event Only"),
+            RouteRestriction::RunOnly => write!(f, "Run Only"),
+            RouteRestriction::MinimumLevel(level) => write!(f, "Level {level}+"),
+            RouteRestriction::Other(restriction) => write!(f, "{restriction}"),
+        }
+    }
+}
+
+/// A row of the Zwift Insider routes table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    pub name: String,
+    /// The route's world, unless the table has no world column or names a
+    /// world that isn't known
+    pub world: Option<World>,
+    pub distance: Distance,
+    pub elevation: Elevation,
+    pub lead_in_distance: Distance,
+    pub lead_in_elevation: Elevation,
+    pub badge_xp: Option<u32>,
+    pub sport: Sport,
+    pub restrictions: BTreeSet<RouteRestriction>,
+    /// Link to the route's detail page
+    pub detail_url: Option<String>,
+}
+
+impl Route {
+    /// Whether a rider at `level` can ride the route outside of events.
+    pub fn is_free_rideable(&self, level: u32) -> bool {
+        self.restrictions
+            .iter()
+            .all(|restriction| match restriction {
+                RouteRestriction::EventOnly => false,
+                RouteRestriction::MinimumLevel(minimum) => level >= *minimum,
+                RouteRestriction::RunOnly | RouteRestriction::Other(_) => true,
+            })
+    }
+
+    /// The name of the route's world, or "Unknown".
+    pub fn world_name(&self) -> &'static str {
+        self.world.map_or("Unknown", |world| world.name())
+    }
+
+    pub fn is_event_only(&self) -> bool {
+        self.restrictions.contains(&RouteRestriction::EventOnly)
+    }
+
+    /// Whether `name` refers to this route, ignoring case, punctuation and
+    /// spacing, e.g. "tour of fire and ice" matches "Tour of Fire & Ice".
+    pub fn matches_name(&self, name: &str) -> bool {
+        normalize_name(&self.name) == normalize_name(name)
+    }
+}
+
+/// Finds the route called `name`, see [`Route::matches_name`].
+pub fn find_route<'a>(routes: &'a [Route], name: &str) -> Option<&'a Route> {
+    routes.iter().find(|route| route.matches_name(name))
+}
+
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .replace('&', " and ")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Routes table columns, identified from the table's header row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    World,
+    Distance,
+    Elevation,
+    LeadInDistance,
+    LeadInElevation,
+    /// Badge XP, for one sport if the header names it, e.g. "Run XP"
+    BadgeXp(Option<Sport>),
+    Sport,
+    Restrictions,
+}
+
+fn column_for_header(header: &str) -> Option<Column> {
+    let header = header.to_lowercase();
+    let is_elevation = header.contains("elev") || header.contains("climb");
+    if header.contains("lead") {
+        if is_elevation {
+            Some(Column::LeadInElevation)
+        } else {
+            Some(Column::LeadInDistance)
+        }
+    } else if header.contains("route") || header == "name" {
+        Some(Column::Name)
+    } else if header.contains("world") || header.contains("map") {
+        Some(Column::World)
+    } else if header.contains("length") || header.contains("distance") {
+        Some(Column::Distance)
+    } else if is_elevation {
+        Some(Column::Elevation)
+    } else if header.contains("xp") {
+        Some(Column::BadgeXp(parse_sport(&header)))
+    } else if header.contains("sport") {
+        Some(Column::Sport)
+    } else if header.contains("restriction") || header.contains("note") {
+        Some(Column::Restrictions)
+    } else {
+        None
+    }
+}
+
+/// Parses the first number in `text`, ignoring thousands separators, e.g.
+/// `1,200 XP` is 1200.
+fn parse_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let number: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .filter(|c| *c != ',')
+        .collect();
+    number.parse().ok()
+}
+
+fn parse_sport(text: &str) -> Option<Sport> {
+    let text = text.to_lowercase();
+    if text.contains("run") {
+        Some(Sport::Running)
+    } else if text.contains("cycl") || text.contains("ride") || text.contains("bike") {
+        Some(Sport::Cycling)
+    } else {
+        None
+    }
+}
+
+/// Elements used for icons in the routes table.
+const ICON_ELEMENTS: &[&str] = &["img", "i", "span"];
+
+/// Collects restrictions shown as icons: the `alt`, `title` and `aria-label`
+/// attributes, classes and image file names of the icon elements in a cell.
+/// Other elements, such as the link to the route, are only searched for icons.
+fn collect_icon_restrictions(element: &Element, restrictions: &mut BTreeSet<RouteRestriction>) {
+    for child in &element.children {
+        let Node::Element(child) = child else {
+            continue;
+        };
+        if !ICON_ELEMENTS.contains(&child.name.as_str()) {
+            collect_icon_restrictions(child, restrictions);
+            continue;
+        }
+        let mut hints: Vec<String> = ["alt", "title", "aria-label"]
+            .iter()
+            .filter_map(|name| child.attributes.get(*name).cloned().flatten())
+            .map(|hint| html_query::decode_entities(&hint))
+            .collect();
+        hints.extend(child.classes.iter().cloned());
+        if child.name == "img" {
+            if let Some(Some(src)) = child.attributes.get("src") {
+                let file = src.rsplit('/').next().unwrap_or_default();
+                hints.push(file.split('.').next().unwrap_or_default().to_string());
+            }
+        }
+        restrictions.extend(
+            hints
+                .iter()
+                .filter_map(|hint| RouteRestriction::from_text(hint)),
+        );
+        collect_icon_restrictions(child, restrictions);
+    }
+}
+
+/// Parses the restrictions column, where restrictions are comma separated
+/// text or icons.
+fn parse_restrictions(cell: &Element) -> BTreeSet<RouteRestriction> {
+    let mut restrictions: BTreeSet<_> = html_query::text(cell)
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|restriction| !restriction.is_empty() && *restriction != "-")
+        .map(|restriction| {
+            RouteRestriction::from_text(restriction)
+                .unwrap_or_else(|| RouteRestriction::Other(restriction.to_string()))
+        })
+        .collect();
+    collect_icon_restrictions(cell, &mut restrictions);
+    restrictions
+}
+
+async fn parse_route_row(
+    row: &Element,
+    columns: &[Option<Column>],
+    default_sport: Sport,
+) -> Result<Option<Route>> {
+    let cells = html_query::find(row, "td").await?;
+    if cells.len() != columns.len() {
+        debug!(
+            "Skipping row with {} cells, expected {}",
+            cells.len(),
+            columns.len()
+        );
+        return Ok(None);
+    }
+
+    let mut route = Route {
+        name: String::new(),
+        world: None,
+        distance: Distance::ZERO,
+        elevation: Elevation::ZERO,
+        lead_in_distance: Distance::ZERO,
+        lead_in_elevation: Elevation::ZERO,
+        badge_xp: None,
+        sport: default_sport,
+        restrictions: BTreeSet::new(),
+        detail_url: None,
+    };
+    let mut distance_found = false;
+    let mut sport_found = false;
+    let mut sport_xp = Vec::new();
+    for (cell, column) in cells.iter().zip(columns) {
+        let text = html_query::text(cell);
+        match column {
+            Some(Column::Name) => {
+                route.name = text;
+                route.detail_url = html_query::find(cell, "a")
+                    .await?
+                    .first()
+                    .and_then(|link| link.attributes.get("href").cloned().flatten())
+                    .map(|href| html_query::decode_entities(&href));
+                // Icons next to the name, e.g. a lock for level locked routes
+                collect_icon_restrictions(cell, &mut route.restrictions);
+            }
+            Some(Column::World) => match text.parse() {
+                Ok(world) => route.world = Some(world),
+                Err(err) => debug!("Unknown world for route \"{}\": {err}", route.name),
+            },
+            Some(Column::Distance) => {
+                if let Ok(distance) = text.parse() {
+                    route.distance = distance;
+                    distance_found = true;
+                }
+            }
+            Some(Column::Elevation) => route.elevation = text.parse().unwrap_or_default(),
+            Some(Column::LeadInDistance) => {
+                route.lead_in_distance = text.parse().unwrap_or_default()
+            }
+            Some(Column::LeadInElevation) => {
+                route.lead_in_elevation = text.parse().unwrap_or_default()
+            }
+            Some(Column::BadgeXp(None)) => route.badge_xp = parse_number(&text).map(|xp| xp as u32),
+            Some(Column::BadgeXp(Some(sport))) => {
+                sport_xp.push((*sport, parse_number(&text).map(|xp| xp as u32)))
+            }
+            Some(Column::Sport) => {
+                if let Some(sport) = parse_sport(&text) {
+                    route.sport = sport;
+                    sport_found = true;
+                }
+            }
+            Some(Column::Restrictions) => route.restrictions.extend(parse_restrictions(cell)),
+            None => {}
+        }
+    }
+
+    if route.name.is_empty() || !distance_found {
+        debug!("Skipping row without a route name and distance: {route:?}");
+        return Ok(None);
+    }
+    if !sport_found && route.restrictions.contains(&RouteRestriction::RunOnly) {
+        route.sport = Sport::Running;
+    }
+    // Tables with XP columns for each sport award XP by the route's sport
+    if let Some((_, xp)) = sport_xp.iter().find(|(sport, _)| *sport == route.sport) {
+        route.badge_xp = *xp;
+    }
+
+    Ok(Some(route))
+}
+
+/// Parses the routes tables on a page. Routes are for `default_sport` unless
+/// the table has a sport column or marks them run only.
+///
+/// Columns are identified by their header text, so tables without a route
+/// name and length column (legends, calendars, etc.) are ignored.
+async fn parse_routes_tables(dom: &Dom, default_sport: Sport) -> Result<Vec<Route>> {
+    let mut routes = Vec::new();
+    for table in html_query::select(dom, "table").await? {
+        let rows = html_query::find(table, "tr").await?;
+        let mut columns = None;
+        for row in rows {
+            match &columns {
+                None => {
+                    let headers = html_query::find(row, "th").await?;
+                    if headers.is_empty() {
+                        continue;
+                    }
+                    let header_columns: Vec<_> = headers
+                        .iter()
+                        .map(|header| column_for_header(&html_query::text(header)))
+                        .collect();
+                    if !header_columns.contains(&Some(Column::Name))
+                        || !header_columns.contains(&Some(Column::Distance))
+                    {
+                        debug!("Skipping table with headers {header_columns:?}");
+                        break;
+                    }
+                    columns = Some(header_columns);
+                }
+                Some(columns) => {
+                    if let Some(route) = parse_route_row(row, columns, default_sport).await? {
+                        routes.push(route);
+                    }
+                }
+            }
+        }
+    }
+
+    if routes.is_empty() {
+        Err(anyhow!("No routes table found"))
+    } else {
+        Ok(routes)
+    }
+}
+
+/// Parses the routes table(s) on the Zwift Insider routes page.
+pub async fn parse_routes(dom: &Dom) -> Result<Vec<Route>> {
+    parse_routes_tables(dom, Sport::Cycling).await
+}
+
+/// Parses the routes table(s) on the Zwift Insider running routes page.
+pub async fn parse_running_routes(dom: &Dom) -> Result<Vec<Route>> {
+    parse_routes_tables(dom, Sport::Running).await
+}
+
+/// Adds the routes in `other` that aren't already in `routes`, for combining
+/// the cycling and running routes pages.
+pub fn merge_routes(routes: &mut Vec<Route>, other: Vec<Route>) {
+    for route in other {
+        let duplicate = routes.iter().any(|existing| {
+            existing.sport == route.sport
+                && existing.world == route.world
+                && existing.matches_name(&route.name)
+        });
+        if !duplicate {
+            routes.push(route);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use html_parser::{Dom, Element};
+
+    use super::{
+        collect_icon_restrictions, find_route, merge_routes, parse_number, parse_restrictions,
+        parse_routes, parse_running_routes, RouteRestriction, Sport,
+    };
+    use crate::{
+        units::{Distance, Elevation},
+        world::World,
+    };
+
+    static ROUTES_HTML: &str = include_str!("../../fixtures/insider/routes.html");
+    static RUNNING_ROUTES_HTML: &str = include_str!("../../fixtures/insider/running-routes.html");
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("340"), Some(340.0));
+        assert_eq!(parse_number("1,200 XP"), Some(1200.0));
+        assert_eq!(parse_number("-"), None);
+        assert_eq!(parse_number(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_routes() {
+        let dom = Dom::parse(ROUTES_HTML).unwrap();
+        let routes = parse_routes(&dom).await.unwrap();
+        assert_eq!(routes.len(), 7);
+
+        let tempus_fugit = &routes[0];
+        assert_eq!(tempus_fugit.name, "Tempus Fugit");
+        assert_eq!(tempus_fugit.world, Some(World::Watopia));
+        assert_eq!(tempus_fugit.world_name(), "Watopia");
+        assert_eq!(tempus_fugit.distance, Distance::from_km(17.3));
+        assert_eq!(tempus_fugit.elevation, Elevation::from_meters(16.0));
+        assert_eq!(tempus_fugit.lead_in_distance, Distance::from_km(0.4));
+        assert_eq!(tempus_fugit.lead_in_elevation, Elevation::from_meters(2.0));
+        assert_eq!(tempus_fugit.badge_xp, Some(340));
+        assert_eq!(tempus_fugit.sport, Sport::Cycling);
+        assert!(tempus_fugit.restrictions.is_empty());
+        assert!(tempus_fugit.is_free_rideable(1));
+        assert_eq!(
+            tempus_fugit.detail_url.as_deref(),
+            Some("https://zwiftinsider.com/route/tempus-fugit/")
+        );
+
+        let road_to_sky = &routes[1];
+        assert_eq!(road_to_sky.elevation, Elevation::from_meters(1036.0));
+        assert_eq!(
+            road_to_sky.restrictions,
+            BTreeSet::from([RouteRestriction::MinimumLevel(6)])
+        );
+        assert!(!road_to_sky.is_free_rideable(5));
+        assert!(road_to_sky.is_free_rideable(6));
+
+        // The link title mentions events, but the route isn't event only
+        let london_flat = &routes[2];
+        assert!(london_flat.restrictions.is_empty());
+        assert!(london_flat.is_free_rideable(1));
+
+        let fire_and_ice = &routes[3];
+        assert_eq!(fire_and_ice.name, "Tour of Fire & Ice");
+        assert_eq!(fire_and_ice.lead_in_distance, Distance::ZERO);
+        assert_eq!(fire_and_ice.badge_xp, None);
+        assert_eq!(
+            fire_and_ice.restrictions,
+            BTreeSet::from([RouteRestriction::EventOnly])
+        );
+        assert!(!fire_and_ice.is_free_rideable(100));
+
+        assert_eq!(routes[5].world, Some(World::MakuriIslands));
+        assert_eq!(routes[6].sport, Sport::Running);
+        assert_eq!(
+            routes[6].restrictions,
+            BTreeSet::from([RouteRestriction::RunOnly])
+        );
+    }
+
+    #[test]
+    fn test_parse_restrictions() {
+        let cell = |html: &str| -> Element {
+            let dom = Dom::parse(&format!("<td>{html}</td>")).unwrap();
+            match &dom.children[0] {
+                html_parser::Node::Element(cell) => cell.clone(),
+                _ => panic!("Expected element"),
+            }
+        };
+        assert_eq!(
+            parse_restrictions(&cell("Event Only; Lvl 12, Tron bike only")),
+            BTreeSet::from([
+                RouteRestriction::EventOnly,
+                RouteRestriction::MinimumLevel(12),
+                RouteRestriction::Other("Tron bike only".to_string())
+            ])
+        );
+        assert_eq!(
+            parse_restrictions(&cell(
+                "<i class=\"icon level-20\"></i><img src=\"/icons/event_only.svg\">"
+            )),
+            BTreeSet::from([
+                RouteRestriction::EventOnly,
+                RouteRestriction::MinimumLevel(20)
+            ])
+        );
+        assert!(parse_restrictions(&cell("-")).is_empty());
+        assert_eq!(
+            parse_restrictions(&cell("Events calendar, Level up")),
+            BTreeSet::from([
+                RouteRestriction::Other("Events calendar".to_string()),
+                RouteRestriction::Other("Level up".to_string())
+            ])
+        );
+
+        let mut restrictions = BTreeSet::new();
+        collect_icon_restrictions(
+            &cell(
+                "<a class=\"event-only\" title=\"Level 5 events only\">Route</a>\
+                 <span class=\"icon run-only\"></span>",
+            ),
+            &mut restrictions,
+        );
+        assert_eq!(restrictions, BTreeSet::from([RouteRestriction::RunOnly]));
+        assert_eq!(RouteRestriction::MinimumLevel(6).to_string(), "Level 6+");
+    }
+
+    #[tokio::test]
+    async fn test_parse_running_routes() {
+        let dom = Dom::parse(RUNNING_ROUTES_HTML).unwrap();
+        let running = parse_running_routes(&dom).await.unwrap();
+        assert_eq!(running.len(), 5);
+        assert!(running.iter().all(|route| route.sport == Sport::Running));
+        let tempus_fugit = find_route(&running, "Tempus Fugit").unwrap();
+        assert_eq!(tempus_fugit.badge_xp, Some(170));
+        assert_eq!(
+            running[4].restrictions,
+            BTreeSet::from([RouteRestriction::RunOnly])
+        );
+
+        let mut routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        merge_routes(&mut routes, running);
+        // Jungle Loop is run only, so it's on both pages
+        assert_eq!(routes.len(), 7 + 4);
+        assert_eq!(
+            routes
+                .iter()
+                .filter(|route| route.name == "Tempus Fugit")
+                .map(|route| (route.sport, route.badge_xp))
+                .collect::<Vec<_>>(),
+            [(Sport::Cycling, Some(340)), (Sport::Running, Some(170))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sport_xp_columns() {
+        let dom = Dom::parse(
+            "<table><tr><th>Route</th><th>World</th><th>Length</th><th>Sport</th>\
+             <th>Ride XP</th><th>Run XP</th></tr>\
+             <tr><td>Tempus Fugit</td><td>Watopia</td><td>17.3km</td><td>Cycling</td>\
+             <td>340</td><td>170</td></tr>\
+             <tr><td>Jungle Loop</td><td>Watopia</td><td>7.8km</td><td>Running</td>\
+             <td>-</td><td>150</td></tr></table>",
+        )
+        .unwrap();
+        let routes = parse_routes(&dom).await.unwrap();
+        assert_eq!(routes[0].badge_xp, Some(340));
+        assert_eq!(routes[1].sport, Sport::Running);
+        assert_eq!(routes[1].badge_xp, Some(150));
+        assert_eq!("run".parse::<Sport>().unwrap(), Sport::Running);
+        assert!("swim".parse::<Sport>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_route() {
+        let dom = Dom::parse(ROUTES_HTML).unwrap();
+        let routes = parse_routes(&dom).await.unwrap();
+        let fire_and_ice = find_route(&routes, "tour of fire and ice").unwrap();
+        assert_eq!(fire_and_ice.name, "Tour of Fire & Ice");
+        assert!(find_route(&routes, "ROAD TO  SKY").is_some());
+        assert!(find_route(&routes, "Road to").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_parse_routes_unknown_world() {
+        let dom = Dom::parse(
+            "<table><tr><th>Route</th><th>World</th><th>Length</th></tr>\
+             <tr><td>Mystery Loop</td><td>Atlantis</td><td>10km</td></tr>\
+             <tr><td>Volcano Flat</td><td>watopia</td><td>12.3km</td></tr></table>",
+        )
+        .unwrap();
+        let routes = parse_routes(&dom).await.unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].name, "Mystery Loop");
+        assert_eq!(routes[0].world, None);
+        assert_eq!(routes[0].world_name(), "Unknown");
+        assert_eq!(routes[1].world, Some(World::Watopia));
+
+        // Tables without a world column still list routes
+        let dom = Dom::parse(
+            "<table><tr><th>Route</th><th>Length</th></tr>\
+             <tr><td>Mystery Loop</td><td>10km</td></tr></table>",
+        )
+        .unwrap();
+        let routes = parse_routes(&dom).await.unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].world, None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_routes_without_table() {
+        let dom = Dom::parse("<table><tr><th>Date</th><th>World</th></tr></table>").unwrap();
+        assert!(parse_routes(&dom).await.is_err());
+    }
+}
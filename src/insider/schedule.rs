@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Month, NaiveDate};
+use html_parser::{Dom, Element, Node};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{page, Route};
+use crate::{html_query, world::World};
+
+/// The guest worlds available on each day of the guest world calendar.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSchedule {
+    days: BTreeMap<NaiveDate, Vec<World>>,
+}
+
+impl WorldSchedule {
+    pub fn insert(&mut self, date: NaiveDate, worlds: Vec<World>) {
+        self.days.insert(date, worlds);
+    }
+
+    /// The first and last days in the calendar.
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        Some((*self.days.keys().next()?, *self.days.keys().next_back()?))
+    }
+
+    /// The guest worlds on `date`, or `None` if the calendar doesn't cover it.
+    pub fn guest_worlds(&self, date: NaiveDate) -> Option<&[World]> {
+        self.days.get(&date).map(Vec::as_slice)
+    }
+
+    /// Whether `world` can be ridden outside of events on `date`. Watopia
+    /// always can, event only worlds never can.
+    pub fn is_available(&self, world: World, date: NaiveDate) -> bool {
+        if world.is_event_only() {
+            false
+        } else if !world.is_guest_world() {
+            true
+        } else {
+            self.guest_worlds(date)
+                .is_some_and(|worlds| worlds.contains(&world))
+        }
+    }
+
+    /// The worlds that can be ridden outside of events on `date`.
+    pub fn available_worlds(&self, date: NaiveDate) -> Vec<World> {
+        World::ALL
+            .into_iter()
+            .filter(|world| self.is_available(*world, date))
+            .collect()
+    }
+
+    /// The routes in worlds that can be ridden on `date`, other than event
+    /// only routes. Routes in an unknown world are left out.
+    pub fn rideable_routes<'a>(&self, routes: &'a [Route], date: NaiveDate) -> Vec<&'a Route> {
+        routes
+            .iter()
+            .filter(|route| !route.is_event_only())
+            .filter(|route| {
+                route
+                    .world
+                    .is_some_and(|world| self.is_available(world, date))
+            })
+            .collect()
+    }
+}
+
+/// Finds the first day of the month named in `text`, e.g. "October 2026".
+fn parse_month(text: &str) -> Option<NaiveDate> {
+    let words: Vec<_> = text.split_whitespace().collect();
+    let month = words.iter().find_map(|word| word.parse::<Month>().ok())?;
+    let year = words
+        .iter()
+        .find_map(|word| word.parse::<i32>().ok().filter(|year| *year > 2000))?;
+    NaiveDate::from_ymd_opt(year, month.number_from_month(), 1)
+}
+
+/// Parses a calendar cell such as "18 New York Yorkshire" into the day of the
+/// month and the worlds. World names are matched longest first, so "Makuri
+/// Islands" isn't read as "Makuri" followed by an unknown "Islands".
+fn parse_day(text: &str) -> Option<(u32, Vec<World>)> {
+    let mut words = text.split_whitespace();
+    let day = words.next()?.parse().ok()?;
+    let words: Vec<_> = words.collect();
+
+    let mut worlds = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let matched = (start + 1..=words.len().min(start + 3))
+            .rev()
+            .find_map(|end| Some((end, words[start..end].join(" ").parse().ok()?)));
+        match matched {
+            Some((end, world)) => {
+                if !worlds.contains(&world) {
+                    worlds.push(world);
+                }
+                start = end;
+            }
+            None => {
+                debug!("Ignoring \"{}\" on day {day}", words[start]);
+                start += 1;
+            }
+        }
+    }
+    Some((day, worlds))
+}
+
+async fn parse_calendar_table(
+    table: &Element,
+    month: NaiveDate,
+    schedule: &mut WorldSchedule,
+) -> Result<()> {
+    for cell in html_query::find(table, "td").await? {
+        let Some((day, worlds)) = parse_day(&html_query::text(cell)) else {
+            continue;
+        };
+        match month.with_day(day) {
+            Some(date) if !worlds.is_empty() => schedule.insert(date, worlds),
+            _ => debug!("Skipping day {day} of {month}"),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the Zwift Insider guest world calendar.
+///
+/// Each month is a table of days, headed or captioned with the month and year,
+/// e.g. "October 2026".
+pub async fn parse_world_schedule(dom: &Dom) -> Result<WorldSchedule> {
+    let mut schedule = WorldSchedule::default();
+    for section in page::sections(dom).await {
+        let section_month = parse_month(&section.heading);
+        for node in &section.nodes {
+            let Node::Element(element) = node else {
+                continue;
+            };
+            let tables = if element.name == "table" {
+                vec![element]
+            } else {
+                html_query::find(element, "table").await?
+            };
+            for table in tables {
+                let caption = html_query::find(table, "caption").await?;
+                let month = caption
+                    .first()
+                    .and_then(|caption| parse_month(&html_query::text(caption)))
+                    .or(section_month);
+                match month {
+                    Some(month) => parse_calendar_table(table, month, &mut schedule).await?,
+                    None => debug!("Skipping table without a month"),
+                }
+            }
+        }
+    }
+
+    if schedule.days.is_empty() {
+        Err(anyhow!("No guest world calendar found"))
+    } else {
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use html_parser::Dom;
+
+    use super::{parse_day, parse_month, parse_world_schedule};
+    use crate::{insider::parse_routes, world::World};
+
+    static CALENDAR_HTML: &str = include_str!("../../fixtures/insider/guest-world-calendar.html");
+    static ROUTES_HTML: &str = include_str!("../../fixtures/insider/routes.html");
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_month() {
+        assert_eq!(parse_month("October 2026"), Some(date(10, 1)));
+        assert_eq!(parse_month("Calendar for Nov 2026"), Some(date(11, 1)));
+        assert_eq!(parse_month("Notes"), None);
+    }
+
+    #[test]
+    fn test_parse_day() {
+        assert_eq!(
+            parse_day("7 Makuri Islands New York"),
+            Some((7, vec![World::MakuriIslands, World::NewYork]))
+        );
+        assert_eq!(parse_day("12 London Mars"), Some((12, vec![World::London])));
+        assert_eq!(parse_day(""), None);
+        assert_eq!(parse_day("London"), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_world_schedule() {
+        let dom = Dom::parse(CALENDAR_HTML).unwrap();
+        let schedule = parse_world_schedule(&dom).await.unwrap();
+        assert_eq!(schedule.date_range(), Some((date(10, 1), date(11, 30))));
+        assert_eq!(
+            schedule.guest_worlds(date(10, 18)),
+            Some([World::London, World::Yorkshire].as_slice())
+        );
+        assert_eq!(
+            schedule.guest_worlds(date(11, 30)),
+            Some([World::NewYork, World::Innsbruck].as_slice())
+        );
+        assert_eq!(schedule.guest_worlds(date(12, 1)), None);
+
+        assert_eq!(
+            schedule.available_worlds(date(10, 1)),
+            [World::Watopia, World::London, World::Scotland]
+        );
+        // Watopia is available even beyond the end of the calendar
+        assert_eq!(schedule.available_worlds(date(12, 1)), [World::Watopia]);
+    }
+
+    #[tokio::test]
+    async fn test_rideable_routes() {
+        let schedule = parse_world_schedule(&Dom::parse(CALENDAR_HTML).unwrap())
+            .await
+            .unwrap();
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+
+        let rideable = schedule.rideable_routes(&routes, date(10, 18));
+        assert!(rideable.iter().all(|route| matches!(
+            route.world,
+            Some(World::Watopia | World::London | World::Yorkshire)
+        )));
+        assert!(rideable
+            .iter()
+            .any(|route| route.name == "Greatest London Flat"));
+        assert!(!rideable
+            .iter()
+            .any(|route| route.name == "Castle to Castle"));
+    }
+}
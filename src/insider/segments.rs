@@ -0,0 +1,421 @@
+use std::{collections::HashMap, fmt};
+
+use anyhow::{anyhow, Result};
+use html_parser::{Dom, Element};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{page, Route, RouteDetails};
+use crate::{html_query, units::Distance, world::World};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SegmentType {
+    Sprint,
+    Kom,
+    Lap,
+}
+
+impl SegmentType {
+    /// Identifies the segment type from text such as "QOM", "Sprint" or
+    /// "Zwift Laps".
+    fn from_text(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        if text.contains("sprint") {
+            Some(SegmentType::Sprint)
+        } else if text.contains("kom") || text.contains("qom") || text.contains("climb") {
+            Some(SegmentType::Kom)
+        } else if text.contains("lap") {
+            Some(SegmentType::Lap)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for SegmentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SegmentType::Sprint => write!(f, "Sprint"),
+            SegmentType::Kom => write!(f, "KOM"),
+            SegmentType::Lap => write!(f, "Lap"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+impl Direction {
+    fn from_text(text: &str) -> Option<Self> {
+        let words: Vec<_> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .collect();
+        if words.iter().any(|word| word == "reverse" || word == "rev") {
+            Some(Direction::Reverse)
+        } else if words.iter().any(|word| word == "forward" || word == "fwd") {
+            Some(Direction::Forward)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Forward => write!(f, "Forward"),
+            Direction::Reverse => write!(f, "Reverse"),
+        }
+    }
+}
+
+/// A timed segment: a sprint, KOM or lap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub name: String,
+    pub segment_type: SegmentType,
+    pub world: World,
+    pub length: Distance,
+    /// Average grade in percent
+    pub average_grade: Option<f64>,
+    pub direction: Option<Direction>,
+}
+
+/// Segment table columns, identified from the table's header row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    World,
+    Type,
+    Length,
+    Grade,
+    Direction,
+}
+
+fn column_for_header(header: &str) -> Option<Column> {
+    let header = header.to_lowercase();
+    if header.contains("segment") || header == "name" {
+        Some(Column::Name)
+    } else if header.contains("world") || header.contains("map") {
+        Some(Column::World)
+    } else if header.contains("type") {
+        Some(Column::Type)
+    } else if header.contains("length") || header.contains("distance") {
+        Some(Column::Length)
+    } else if header.contains("grade") || header.contains("gradient") {
+        Some(Column::Grade)
+    } else if header.contains("direction") {
+        Some(Column::Direction)
+    } else {
+        None
+    }
+}
+
+/// Parses a grade such as `8.5%`.
+pub(super) fn parse_grade(text: &str) -> Option<f64> {
+    text.trim().trim_end_matches('%').trim().parse().ok()
+}
+
+async fn parse_segment_row(
+    row: &Element,
+    columns: &[Option<Column>],
+    page_type: Option<SegmentType>,
+) -> Result<Option<Segment>> {
+    let cells = html_query::find(row, "td").await?;
+    if cells.len() != columns.len() {
+        debug!(
+            "Skipping row with {} cells, expected {}",
+            cells.len(),
+            columns.len()
+        );
+        return Ok(None);
+    }
+
+    let mut name = String::new();
+    let mut world = None;
+    let mut segment_type = None;
+    let mut length = None;
+    let mut average_grade = None;
+    let mut direction = None;
+    for (cell, column) in cells.iter().zip(columns) {
+        let text = html_query::text(cell);
+        match column {
+            Some(Column::Name) => name = text,
+            Some(Column::World) => match text.parse() {
+                Ok(parsed) => world = Some(parsed),
+                Err(err) => {
+                    warn!("Skipping segment \"{name}\": {err}");
+                    return Ok(None);
+                }
+            },
+            Some(Column::Type) => segment_type = SegmentType::from_text(&text),
+            Some(Column::Length) => length = text.parse().ok(),
+            Some(Column::Grade) => average_grade = parse_grade(&text),
+            Some(Column::Direction) => direction = Direction::from_text(&text),
+            None => {}
+        }
+    }
+
+    // Pages listing a single type of segment don't have a type column
+    let segment_type = segment_type
+        .or_else(|| SegmentType::from_text(&name))
+        .or(page_type);
+    let (Some(world), Some(segment_type), Some(length)) = (world, segment_type, length) else {
+        debug!("Skipping row without a segment world, type and length: \"{name}\"");
+        return Ok(None);
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Segment {
+        direction: direction.or_else(|| Direction::from_text(&name)),
+        name,
+        segment_type,
+        world,
+        length,
+        average_grade,
+    }))
+}
+
+/// The segment type a page lists, if its title names exactly one type.
+fn page_type(title: &str) -> Option<SegmentType> {
+    let types: Vec<_> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(SegmentType::from_text)
+        .collect();
+    match types.as_slice() {
+        [segment_type, rest @ ..] if rest.iter().all(|other| other == segment_type) => {
+            Some(*segment_type)
+        }
+        _ => None,
+    }
+}
+
+/// Parses the segment table(s) on a Zwift Insider segment listing page.
+///
+/// Segments without a type column take their type from their name, e.g.
+/// "Epic KOM", or failing that from the page title, e.g. "Zwift Sprints".
+pub async fn parse_segments(dom: &Dom) -> Result<Vec<Segment>> {
+    let page_type = page::title(dom).await.and_then(|title| page_type(&title));
+    let mut segments = Vec::new();
+    for table in html_query::select(dom, "table").await? {
+        let rows = html_query::find(table, "tr").await?;
+        let mut columns = None;
+        for row in rows {
+            match &columns {
+                None => {
+                    let headers = html_query::find(row, "th").await?;
+                    if headers.is_empty() {
+                        continue;
+                    }
+                    let header_columns: Vec<_> = headers
+                        .iter()
+                        .map(|header| column_for_header(&html_query::text(header)))
+                        .collect();
+                    if !header_columns.contains(&Some(Column::Name))
+                        || !header_columns.contains(&Some(Column::Length))
+                    {
+                        debug!("Skipping table with headers {header_columns:?}");
+                        break;
+                    }
+                    columns = Some(header_columns);
+                }
+                Some(columns) => {
+                    if let Some(segment) = parse_segment_row(row, columns, page_type).await? {
+                        segments.push(segment);
+                    }
+                }
+            }
+        }
+    }
+
+    if segments.is_empty() {
+        Err(anyhow!("No segments table found"))
+    } else {
+        Ok(segments)
+    }
+}
+
+/// Identifies a segment regardless of how a page names it, e.g. "Watopia
+/// Sprint" in the segment list is "Watopia Sprint Forward" on route pages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SegmentKey {
+    world: World,
+    segment_type: SegmentType,
+    name: String,
+}
+
+/// Normalizes a segment name for comparison: lowercase words without
+/// "KOM", "sprint" and the like, ending in "reverse" for reverse segments.
+pub(super) fn normalize_segment_name(name: &str) -> String {
+    let mut reverse = false;
+    let mut words = Vec::new();
+    for word in name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let word = word.to_lowercase();
+        match word.as_str() {
+            "reverse" | "rev" => reverse = true,
+            "kom" | "qom" | "sprint" | "lap" | "segment" | "forward" | "fwd" => {}
+            _ => words.push(word),
+        }
+    }
+    if reverse {
+        words.push("reverse".to_string());
+    }
+    words.join(" ")
+}
+
+impl SegmentKey {
+    fn new(world: World, segment_type: SegmentType, name: &str) -> Self {
+        SegmentKey {
+            world,
+            segment_type,
+            name: normalize_segment_name(name),
+        }
+    }
+
+    fn for_segment(segment: &Segment) -> Self {
+        let name = match segment.direction {
+            Some(Direction::Reverse) => format!("{} reverse", segment.name),
+            _ => segment.name.clone(),
+        };
+        SegmentKey::new(segment.world, segment.segment_type, &name)
+    }
+}
+
+/// A reverse index from segments to the routes that include them, built from
+/// the KOMs and sprints listed on route detail pages.
+///
+/// Route pages don't name the laps a route includes, so lap segments are
+/// never indexed.
+#[derive(Debug, Default)]
+pub struct SegmentIndex {
+    routes: HashMap<SegmentKey, Vec<String>>,
+}
+
+impl SegmentIndex {
+    pub fn new<'a>(routes: impl IntoIterator<Item = (&'a Route, &'a RouteDetails)>) -> Self {
+        let mut index = SegmentIndex::default();
+        for (route, details) in routes {
+            let Some(world) = route.world else {
+                debug!("Not indexing \"{}\" in an unknown world", route.name);
+                continue;
+            };
+            let segments = details
+                .koms
+                .iter()
+                .map(|kom| (SegmentType::Kom, kom))
+                .chain(
+                    details
+                        .sprints
+                        .iter()
+                        .map(|sprint| (SegmentType::Sprint, sprint)),
+                );
+            for (segment_type, name) in segments {
+                let routes = index
+                    .routes
+                    .entry(SegmentKey::new(world, segment_type, name))
+                    .or_default();
+                if !routes.contains(&route.name) {
+                    routes.push(route.name.clone());
+                }
+            }
+        }
+        index
+    }
+
+    /// The names of the routes that include `segment`.
+    pub fn routes(&self, segment: &Segment) -> &[String] {
+        self.routes
+            .get(&SegmentKey::for_segment(segment))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use html_parser::Dom;
+
+    use super::{page_type, parse_segments, Direction, SegmentIndex, SegmentType};
+    use crate::{
+        insider::{parse_route_details, parse_routes},
+        units::Distance,
+        world::World,
+    };
+
+    static SEGMENTS_HTML: &str = include_str!("../../fixtures/insider/segments.html");
+    static ROUTES_HTML: &str = include_str!("../../fixtures/insider/routes.html");
+    static ROAD_TO_SKY_HTML: &str = include_str!("../../fixtures/insider/route-road-to-sky.html");
+
+    #[tokio::test]
+    async fn test_parse_segments() {
+        let dom = Dom::parse(SEGMENTS_HTML).unwrap();
+        let segments = parse_segments(&dom).await.unwrap();
+        assert_eq!(segments.len(), 6);
+
+        let alpe = &segments[0];
+        assert_eq!(alpe.name, "Alpe du Zwift");
+        assert_eq!(alpe.segment_type, SegmentType::Kom);
+        assert_eq!(alpe.world, World::Watopia);
+        assert_eq!(alpe.length, Distance::from_km(12.2));
+        assert_eq!(alpe.average_grade, Some(8.5));
+        assert_eq!(alpe.direction, Some(Direction::Forward));
+
+        assert_eq!(segments[2].direction, Some(Direction::Reverse));
+        assert_eq!(segments[3].segment_type, SegmentType::Sprint);
+        assert_eq!(segments[4].segment_type, SegmentType::Kom);
+        assert_eq!(segments[5].segment_type, SegmentType::Lap);
+        assert_eq!(segments[5].average_grade, None);
+        assert_eq!(segments[5].direction, None);
+    }
+
+    #[test]
+    fn test_page_type() {
+        assert_eq!(page_type("Zwift Sprints"), Some(SegmentType::Sprint));
+        assert_eq!(page_type("All KOMs and QOMs"), Some(SegmentType::Kom));
+        assert_eq!(page_type("Zwift KOMs, Sprints and Laps"), None);
+        assert_eq!(page_type("Zwift Segments"), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_segments_type_from_title() {
+        let dom = Dom::parse(
+            "<h1 class=\"entry-title\">Zwift Sprints</h1><table>\
+             <tr><th>Segment</th><th>World</th><th>Length</th></tr>\
+             <tr><td>Fox Hill</td><td>London</td><td>0.4km</td></tr>\
+             <tr><td>Epic KOM</td><td>Watopia</td><td>9.4km</td></tr></table>",
+        )
+        .unwrap();
+        let segments = parse_segments(&dom).await.unwrap();
+        assert_eq!(segments[0].segment_type, SegmentType::Sprint);
+        assert_eq!(segments[1].segment_type, SegmentType::Kom);
+    }
+
+    #[tokio::test]
+    async fn test_segment_index() {
+        let segments = parse_segments(&Dom::parse(SEGMENTS_HTML).unwrap())
+            .await
+            .unwrap();
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let road_to_sky = routes.iter().find(|r| r.name == "Road to Sky").unwrap();
+        let details = parse_route_details(&Dom::parse(ROAD_TO_SKY_HTML).unwrap())
+            .await
+            .unwrap();
+
+        let index = SegmentIndex::new([(road_to_sky, &details)]);
+        assert_eq!(index.routes(&segments[0]), ["Road to Sky"]);
+        assert_eq!(index.routes(&segments[3]), ["Road to Sky"]);
+        assert!(index.routes(&segments[1]).is_empty());
+        assert!(index.routes(&segments[2]).is_empty());
+    }
+}
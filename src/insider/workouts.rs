@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use html_parser::{Dom, Node};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::page;
+use crate::html_query;
+
+/// A page of workouts linked from the workouts page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkoutCollection {
+    pub name: String,
+    pub url: String,
+    pub description: Option<String>,
+}
+
+/// A workout described on a collection page, with the link to its `.zwo`
+/// file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkoutListing {
+    pub name: String,
+    pub collection: String,
+    pub description: Option<String>,
+    pub duration: Option<Duration>,
+    /// What the workout trains, e.g. "Threshold" or "Sprint"
+    pub focus: Vec<String>,
+    pub zwo_url: String,
+}
+
+/// Parses a workout duration such as `50 minutes`, `1h 15m` or `1:05`.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    if let Some((hours, minutes)) = text.split_once(':') {
+        let (hours, minutes): (u64, u64) =
+            (hours.trim().parse().ok()?, minutes.trim().parse().ok()?);
+        return Some(Duration::from_secs((hours * 60 + minutes) * 60));
+    }
+
+    // Pairs of a number and a unit, where the unit may be attached
+    let mut minutes = 0.0;
+    let mut found = false;
+    let mut number: Option<f64> = None;
+    let tokens = text
+        .split(|c: char| c.is_whitespace())
+        .flat_map(|word| {
+            let split = word
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(word.len());
+            [&word[..split], &word[split..]]
+        })
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        if let Ok(value) = token.parse::<f64>() {
+            number = Some(value);
+        } else if let Some(value) = number.take() {
+            if token.starts_with('h') {
+                minutes += value * 60.0;
+                found = true;
+            } else if token.starts_with('m') {
+                minutes += value;
+                found = true;
+            } else if token.starts_with('s') {
+                minutes += value / 60.0;
+                found = true;
+            }
+        }
+    }
+    found.then(|| Duration::from_secs_f64(minutes * 60.0))
+}
+
+/// Splits a focus list such as `Threshold, Sprint`.
+fn parse_focus(value: &str) -> Vec<String> {
+    value
+        .split([',', '/', '&'])
+        .map(str::trim)
+        .filter(|focus| !focus.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the Zwift Insider workouts page into the collections it links to.
+///
+/// Collections are the list items linked under headings mentioning
+/// "collection", with any text after the link as the description.
+pub async fn parse_workout_collections(dom: &Dom) -> Result<Vec<WorkoutCollection>> {
+    let mut collections = Vec::new();
+    for section in page::sections(dom).await {
+        if !section.heading.to_lowercase().contains("collection") {
+            continue;
+        }
+        for node in &section.nodes {
+            let Node::Element(element) = node else {
+                continue;
+            };
+            for item in html_query::find(element, "li").await? {
+                let Some(link) = html_query::find(item, "a").await?.into_iter().next() else {
+                    continue;
+                };
+                let Some(Some(url)) = link.attributes.get("href") else {
+                    continue;
+                };
+                let name = html_query::text(link);
+                let description = html_query::text(item)
+                    .strip_prefix(&name)
+                    .map(|rest| rest.trim_start_matches([' ', '-', '\u{2013}', '\u{2014}', ':']))
+                    .filter(|rest| !rest.is_empty())
+                    .map(str::to_string);
+                collections.push(WorkoutCollection {
+                    name,
+                    url: html_query::decode_entities(url),
+                    description,
+                });
+            }
+        }
+    }
+
+    if collections.is_empty() {
+        Err(anyhow!("No workout collections found"))
+    } else {
+        Ok(collections)
+    }
+}
+
+/// Parses a workout collection page.
+///
+/// Each workout is a section headed by its name, holding description
+/// paragraphs, `Duration:` and `Focus:` lines and a link to its `.zwo` file.
+/// Sections without a `.zwo` link aren't workouts.
+pub async fn parse_workout_collection(dom: &Dom) -> Result<Vec<WorkoutListing>> {
+    let collection = page::title(dom)
+        .await
+        .ok_or_else(|| anyhow!("Workout collection page has no title"))?;
+
+    let mut workouts = Vec::new();
+    for section in page::sections(dom).await {
+        let lines = section.lines();
+        let Some(zwo_url) = lines
+            .iter()
+            .flat_map(|line| &line.links)
+            .find(|link| link.to_lowercase().ends_with(".zwo"))
+        else {
+            debug!("Skipping \"{}\" without a .zwo link", section.heading);
+            continue;
+        };
+
+        let mut workout = WorkoutListing {
+            name: section.heading.clone(),
+            collection: collection.clone(),
+            description: None,
+            duration: None,
+            focus: Vec::new(),
+            zwo_url: zwo_url.clone(),
+        };
+        let mut description = Vec::new();
+        for line in lines.iter().filter(|line| line.links.is_empty()) {
+            match line.label_value() {
+                Some((label, value)) if label.eq_ignore_ascii_case("duration") => {
+                    workout.duration = parse_duration(value)
+                }
+                Some((label, value)) if label.eq_ignore_ascii_case("focus") => {
+                    workout.focus = parse_focus(value)
+                }
+                Some((label, _)) if label.eq_ignore_ascii_case("tss") => {}
+                _ => description.push(line.text.as_str()),
+            }
+        }
+        workout.description = Some(description.join("\n")).filter(|text| !text.is_empty());
+        workouts.push(workout);
+    }
+
+    if workouts.is_empty() {
+        Err(anyhow!("No workouts found on \"{collection}\""))
+    } else {
+        Ok(workouts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use html_parser::Dom;
+
+    use super::{parse_duration, parse_workout_collection, parse_workout_collections};
+
+    static WORKOUTS_HTML: &str = include_str!("../../fixtures/insider/workouts.html");
+    static COLLECTION_HTML: &str = include_str!("../../fixtures/insider/workout-collection.html");
+
+    fn minutes(minutes: u64) -> Option<Duration> {
+        Some(Duration::from_secs(minutes * 60))
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("50 minutes"), minutes(50));
+        assert_eq!(parse_duration("1h 15m"), minutes(75));
+        assert_eq!(parse_duration("1 hr 5 min"), minutes(65));
+        assert_eq!(parse_duration("1:05"), minutes(65));
+        assert_eq!(parse_duration("90 seconds"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("about an hour"), None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_workout_collections() {
+        let dom = Dom::parse(WORKOUTS_HTML).unwrap();
+        let collections = parse_workout_collections(&dom).await.unwrap();
+        assert_eq!(collections.len(), 3);
+        assert_eq!(collections[0].name, "Threshold Builders");
+        assert_eq!(
+            collections[0].url,
+            "https://zwiftinsider.com/workouts/threshold-builders/"
+        );
+        assert_eq!(
+            collections[0].description.as_deref(),
+            Some("over-unders and long intervals to raise your FTP")
+        );
+        assert_eq!(collections[1].url, "/workouts/recovery-spins/");
+        assert_eq!(collections[1].description, None);
+    }
+
+    #[tokio::test]
+    async fn test_parse_workout_collection() {
+        let dom = Dom::parse(COLLECTION_HTML).unwrap();
+        let workouts = parse_workout_collection(&dom).await.unwrap();
+        assert_eq!(workouts.len(), 3);
+
+        let over_unders = &workouts[0];
+        assert_eq!(over_unders.name, "Over-Unders & Sprints");
+        assert_eq!(over_unders.collection, "Threshold Builders");
+        assert_eq!(
+            over_unders.description.as_deref(),
+            Some("Threshold over-unders followed by seated sprints. Keep your cadence high on the \u{201c}overs\u{201d}.")
+        );
+        assert_eq!(over_unders.duration, minutes(50));
+        assert_eq!(over_unders.focus, ["Threshold", "Sprint"]);
+        assert_eq!(
+            over_unders.zwo_url,
+            "https://zwiftinsider.com/wp-content/uploads/2026/03/Over-Unders.zwo"
+        );
+
+        let sweet_spot = &workouts[1];
+        assert_eq!(
+            sweet_spot.description.as_deref(),
+            Some(
+                "Three long sweet spot efforts with five minutes recovery between them.\n\
+                 Note: ride these seated."
+            )
+        );
+        assert_eq!(sweet_spot.duration, minutes(75));
+        assert_eq!(
+            sweet_spot.zwo_url,
+            "/wp-content/uploads/2026/03/Sweet-Spot-3x15.zwo"
+        );
+        assert_eq!(workouts[2].duration, minutes(65));
+    }
+
+    #[tokio::test]
+    async fn test_parse_workout_collection_without_workouts() {
+        let dom =
+            Dom::parse("<h1>Zwift Update</h1><h3>New</h3><p>Nothing to download</p>").unwrap();
+        assert!(parse_workout_collection(&dom).await.is_err());
+    }
+}
@@ -1 +1,18 @@
+pub mod badges;
+pub mod catalogue;
+pub mod diff;
+pub mod events;
+pub mod export;
 pub mod html_query;
+pub mod insider;
+pub mod physics;
+pub mod profile;
+pub mod progression;
+pub mod recommend;
+pub mod snapshot;
+pub mod units;
+pub mod workout;
+pub mod world;
+
+pub use units::{Distance, Elevation, UnitSystem};
+pub use world::{BoundingBox, World};
@@ -0,0 +1,310 @@
+//! A cycling power balance model, used to estimate how long routes take.
+
+use std::time::Duration;
+
+use crate::units::{Distance, Elevation};
+
+const GRAVITY: f64 = 9.8067;
+const AIR_DENSITY: f64 = 1.225;
+/// Speeds are capped at this on descents, where riders stop pedalling and
+/// Zwift's draft and braking limits take over.
+const MAX_SPEED: f64 = 20.0;
+/// The time step used when riding a profile, in seconds.
+const TIME_STEP: f64 = 0.5;
+/// The slowest speed in m/s when riding a profile. Riders never stop in
+/// Zwift, however steep the climb.
+const MIN_SPEED: f64 = 1.0;
+
+/// The rider and bike parameters that determine speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rider {
+    pub weight_kg: f64,
+    pub bike_weight_kg: f64,
+    /// Drag coefficient times frontal area, in m²
+    pub cda: f64,
+    /// Coefficient of rolling resistance
+    pub crr: f64,
+    /// Sustained power per kg of rider weight
+    pub watts_per_kg: f64,
+}
+
+impl Default for Rider {
+    fn default() -> Self {
+        Rider {
+            weight_kg: 75.0,
+            bike_weight_kg: 8.0,
+            cda: 0.32,
+            crr: 0.004,
+            watts_per_kg: 3.0,
+        }
+    }
+}
+
+impl Rider {
+    pub fn power(&self) -> f64 {
+        self.weight_kg * self.watts_per_kg
+    }
+
+    fn total_mass(&self) -> f64 {
+        self.weight_kg + self.bike_weight_kg
+    }
+
+    /// The forces opposing the rider, in N: gravity and rolling resistance on
+    /// `grade` and drag at `speed`.
+    fn resistance(&self, grade: f64, speed: f64) -> f64 {
+        let angle = grade.atan();
+        let mass = self.total_mass();
+        mass * GRAVITY * (angle.sin() + self.crr * angle.cos())
+            + 0.5 * AIR_DENSITY * self.cda * speed * speed
+    }
+
+    /// The steady speed in m/s at which the rider's power balances gravity,
+    /// rolling resistance and drag on `grade`, e.g. 0.05 for 5%.
+    pub fn speed(&self, grade: f64) -> f64 {
+        let power = self.power();
+
+        // resistance(v) * v - power has exactly one positive root
+        let (mut low, mut high) = (0.0_f64, 100.0_f64);
+        for _ in 0..100 {
+            let speed = (low + high) / 2.0;
+            if self.resistance(grade, speed) * speed > power {
+                high = speed;
+            } else {
+                low = speed;
+            }
+        }
+        low.min(MAX_SPEED)
+    }
+
+    /// Estimates the time to ride a course given as `(distance, elevation)`
+    /// samples in order of distance, starting from walking pace.
+    ///
+    /// The power balance is integrated over time, so the rider carries speed
+    /// from descents into the following climbs, as they do in Zwift. Grades
+    /// are linear between samples.
+    pub fn ride_time(&self, profile: &[(Distance, Elevation)]) -> Duration {
+        self.ride_times(profile).last().copied().unwrap_or_default()
+    }
+
+    /// The time at which the rider reaches each sample of `profile`, timed
+    /// as in [`Rider::ride_time`].
+    pub fn ride_times(&self, profile: &[(Distance, Elevation)]) -> Vec<Duration> {
+        let mass = self.total_mass();
+        let power = self.power();
+        let start = profile.first().map(|(distance, _)| distance.meters());
+        let (mut distance, mut speed, mut time) = (start.unwrap_or_default(), MIN_SPEED, 0.0);
+        let mut times = Vec::with_capacity(profile.len());
+        if !profile.is_empty() {
+            times.push(Duration::ZERO);
+        }
+        for pair in profile.windows(2) {
+            let ((start, start_elevation), (end, end_elevation)) = (pair[0], pair[1]);
+            let length = end.meters() - start.meters();
+            if length <= 0.0 {
+                times.push(Duration::from_secs_f64(time));
+                continue;
+            }
+            let grade = (end_elevation.meters() - start_elevation.meters()) / length;
+            while distance < end.meters() {
+                let force = power / speed - self.resistance(grade, speed);
+                let next_speed = (speed + force / mass * TIME_STEP).clamp(MIN_SPEED, MAX_SPEED);
+                let step = (speed + next_speed) / 2.0 * TIME_STEP;
+                if distance + step >= end.meters() {
+                    // Only count the part of the step needed to reach the sample
+                    time += TIME_STEP * (end.meters() - distance) / step;
+                    distance = end.meters();
+                } else {
+                    time += TIME_STEP;
+                    distance += step;
+                }
+                speed = next_speed;
+            }
+            times.push(Duration::from_secs_f64(time));
+        }
+        times
+    }
+
+    /// Estimates the time to ride `distance` with `elevation` gain when there
+    /// is no profile, assuming the climbing is spread evenly over the first
+    /// half of the distance and the second half descends the same height.
+    ///
+    /// This assumes the route finishes where it started. Routes finishing at
+    /// a summit, such as Road to Sky, have no descent, so their estimate is
+    /// only rough; use [`Rider::ride_time`] with their profile instead.
+    pub fn estimate_time(&self, distance: Distance, elevation: Elevation) -> Duration {
+        self.ride_time(&summary_profile(distance, elevation))
+    }
+}
+
+/// A profile that climbs `elevation` over the first half of `distance` and
+/// descends it over the second half.
+fn summary_profile(distance: Distance, elevation: Elevation) -> Vec<(Distance, Elevation)> {
+    let half = Distance::from_meters(distance.meters() / 2.0);
+    vec![
+        (Distance::ZERO, Elevation::ZERO),
+        (half, elevation),
+        (distance, Elevation::ZERO),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Rider;
+    use crate::units::{Distance, Elevation};
+
+    #[test]
+    fn test_speed() {
+        let rider = Rider::default();
+        let flat = rider.speed(0.0);
+        // 225 W on the flat is about 36 km/h
+        assert!((flat * 3.6 - 36.0).abs() < 1.5, "{}", flat * 3.6);
+        assert!(rider.speed(0.08) < flat / 2.0);
+        assert!(rider.speed(-0.03) > flat);
+        assert!(rider.speed(-0.2) <= 20.0);
+
+        let stronger = Rider {
+            watts_per_kg: 4.0,
+            ..rider
+        };
+        assert!(stronger.speed(0.05) > rider.speed(0.05));
+    }
+
+    #[test]
+    fn test_ride_time() {
+        let rider = Rider::default();
+        let flat = [
+            (Distance::ZERO, Elevation::ZERO),
+            (Distance::from_km(20.0), Elevation::ZERO),
+        ];
+        // Steady speed plus a few seconds to get up to it
+        let steady = 20000.0 / rider.speed(0.0);
+        let time = rider.ride_time(&flat).as_secs_f64();
+        assert!(time > steady && time < steady + 30.0, "{time} vs {steady}");
+
+        // Speed carried from a descent shortens the following climb
+        let rolling = [
+            (Distance::ZERO, Elevation::from_meters(50.0)),
+            (Distance::from_km(1.0), Elevation::ZERO),
+            (Distance::from_km(1.2), Elevation::from_meters(10.0)),
+        ];
+        let without_momentum = 1000.0 / rider.speed(-0.05) + 200.0 / rider.speed(0.05);
+        assert!(rider.ride_time(&rolling).as_secs_f64() < without_momentum);
+
+        // Walls too steep to ride at the rider's power are still finished
+        let wall = [
+            (Distance::ZERO, Elevation::ZERO),
+            (Distance::from_meters(100.0), Elevation::from_meters(40.0)),
+        ];
+        assert!(rider.ride_time(&wall) <= Duration::from_secs(100));
+
+        // Profiles starting part way along a route are timed from their start
+        let shifted = [
+            (Distance::from_km(5.0), Elevation::ZERO),
+            (Distance::from_km(25.0), Elevation::ZERO),
+        ];
+        assert_eq!(rider.ride_time(&shifted), rider.ride_time(&flat));
+
+        let times = rider.ride_times(&rolling);
+        assert_eq!(times.len(), 3);
+        assert!(times[0].is_zero() && times[1] < times[2]);
+        assert_eq!(times[2], rider.ride_time(&rolling));
+
+        assert!(rider.ride_time(&[]).is_zero());
+        assert!(rider.ride_times(&[]).is_empty());
+        assert!(rider
+            .estimate_time(Distance::ZERO, Elevation::ZERO)
+            .is_zero());
+    }
+
+    /// Zwift Insider's time estimates for a 75 kg rider, in minutes, with
+    /// the page each is published on. The model should stay within 10% of
+    /// them.
+    const CALIBRATION: &[(&str, &str, f64, f64)] = &[
+        // (course, source, W/kg, minutes)
+        (
+            "Alpe du Zwift",
+            "https://zwiftinsider.com/segment/alpe-du-zwift/",
+            2.0,
+            95.0,
+        ),
+        (
+            "Alpe du Zwift",
+            "https://zwiftinsider.com/segment/alpe-du-zwift/",
+            3.0,
+            64.0,
+        ),
+        (
+            "Alpe du Zwift",
+            "https://zwiftinsider.com/segment/alpe-du-zwift/",
+            4.0,
+            49.0,
+        ),
+        (
+            "Road to Sky",
+            "https://zwiftinsider.com/route/road-to-sky/",
+            3.0,
+            72.0,
+        ),
+        (
+            "Tempus Fugit",
+            "https://zwiftinsider.com/route/tempus-fugit/",
+            3.0,
+            30.0,
+        ),
+    ];
+
+    /// The courses of [`CALIBRATION`] as `(distance, elevation)` samples.
+    fn calibration_profile(course: &str) -> Vec<(Distance, Elevation)> {
+        let sample = |km: f64, meters: f64| (Distance::from_km(km), Elevation::from_meters(meters));
+        match course {
+            // 12.2 km at 8.5%
+            "Alpe du Zwift" => vec![sample(0.0, 0.0), sample(12.2, 1036.0)],
+            // Flat roads from the start to the foot of the Alpe, finishing
+            // at the summit
+            "Road to Sky" => vec![sample(0.0, 0.0), sample(5.3, 0.0), sample(17.5, 1036.0)],
+            // Flat, with 16 m of climbing spread over the lap
+            "Tempus Fugit" => vec![sample(0.0, 0.0), sample(8.65, 16.0), sample(17.3, 0.0)],
+            _ => unreachable!("No profile for {course}"),
+        }
+    }
+
+    #[test]
+    fn test_calibration() {
+        for (course, source, watts_per_kg, minutes) in CALIBRATION {
+            let rider = Rider {
+                watts_per_kg: *watts_per_kg,
+                ..Rider::default()
+            };
+            let estimate = rider.ride_time(&calibration_profile(course)).as_secs_f64() / 60.0;
+            assert!(
+                (estimate - minutes).abs() / minutes < 0.1,
+                "{course} at {watts_per_kg} W/kg: {estimate:.1} minutes, {source} gives {minutes}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_time() {
+        let rider = Rider::default();
+
+        // Loops match their profile
+        let tempus_fugit =
+            rider.estimate_time(Distance::from_km(17.3), Elevation::from_meters(16.0));
+        assert_eq!(
+            tempus_fugit,
+            rider.ride_time(&calibration_profile("Tempus Fugit"))
+        );
+
+        // Summit finishes have no descent, but the summary model assumes one,
+        // so they are only roughly estimated
+        let road_to_sky = rider
+            .estimate_time(Distance::from_km(17.5), Elevation::from_meters(1036.0))
+            .as_secs_f64();
+        let profiled = rider
+            .ride_time(&calibration_profile("Road to Sky"))
+            .as_secs_f64();
+        assert!((road_to_sky - profiled).abs() / profiled < 0.1);
+    }
+}
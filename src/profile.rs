@@ -0,0 +1,134 @@
+//! Elevation profiles: a route's elevation sampled along its length.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::units::{Distance, Elevation};
+
+/// The elevation at points along a route, in order of distance from the start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ProfileSamples")]
+pub struct ElevationProfile {
+    samples: Vec<(Distance, Elevation)>,
+}
+
+/// A serialized profile, checked by [`ElevationProfile::new`] when
+/// deserialized.
+#[derive(Deserialize)]
+struct ProfileSamples {
+    samples: Vec<(Distance, Elevation)>,
+}
+
+impl TryFrom<ProfileSamples> for ElevationProfile {
+    type Error = anyhow::Error;
+
+    fn try_from(profile: ProfileSamples) -> Result<Self> {
+        ElevationProfile::new(profile.samples)
+    }
+}
+
+/// A profile sample as embedded in web pages, either `[km, m]` or
+/// `{"distance": km, "elevation": m}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSample {
+    Pair(f64, f64),
+    Object { distance: f64, elevation: f64 },
+}
+
+impl ElevationProfile {
+    /// Creates a profile from at least two samples with increasing distances.
+    pub fn new(samples: Vec<(Distance, Elevation)>) -> Result<Self> {
+        if samples.len() < 2 {
+            return Err(anyhow!("A profile needs at least two samples"));
+        }
+        if samples
+            .windows(2)
+            .any(|pair| pair[1].0.meters() < pair[0].0.meters())
+        {
+            return Err(anyhow!("Profile distances must increase"));
+        }
+        Ok(ElevationProfile { samples })
+    }
+
+    /// Parses a JSON array of samples with distances in km and elevations in
+    /// m, e.g. `[[0, 10], [0.5, 12.5]]`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: Vec<RawSample> = serde_json::from_str(json).context("Invalid profile JSON")?;
+        Self::new(
+            raw.into_iter()
+                .map(|sample| match sample {
+                    RawSample::Pair(distance, elevation)
+                    | RawSample::Object {
+                        distance,
+                        elevation,
+                    } => (
+                        Distance::from_km(distance),
+                        Elevation::from_meters(elevation),
+                    ),
+                })
+                .collect(),
+        )
+    }
+
+    /// The samples, as taken by [`Rider::ride_time`](crate::physics::Rider::ride_time).
+    pub fn samples(&self) -> &[(Distance, Elevation)] {
+        &self.samples
+    }
+
+    pub fn length(&self) -> Distance {
+        let start = self.samples[0].0;
+        let end = self.samples[self.samples.len() - 1].0;
+        Distance::from_meters(end.meters() - start.meters())
+    }
+
+    /// The total height climbed, ignoring descents.
+    pub fn elevation_gain(&self) -> Elevation {
+        Elevation::from_meters(
+            self.samples
+                .windows(2)
+                .map(|pair| (pair[1].1.meters() - pair[0].1.meters()).max(0.0))
+                .sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ElevationProfile;
+    use crate::units::{Distance, Elevation};
+
+    #[test]
+    fn test_from_json() {
+        let pairs =
+            ElevationProfile::from_json("[[0, 10], [0.5, 30], [1.5, 20], [2, 45]]").unwrap();
+        assert_eq!(pairs.samples().len(), 4);
+        assert_eq!(pairs.length(), Distance::from_km(2.0));
+        assert_eq!(pairs.elevation_gain(), Elevation::from_meters(45.0));
+
+        let objects = ElevationProfile::from_json(
+            r#"[{"distance": 0, "elevation": 10}, {"distance": 0.5, "elevation": 30},
+                {"distance": 1.5, "elevation": 20}, {"distance": 2, "elevation": 45}]"#,
+        )
+        .unwrap();
+        assert_eq!(objects, pairs);
+
+        assert!(ElevationProfile::from_json("[[0, 10]]").is_err());
+        assert!(ElevationProfile::from_json("[[1, 10], [0.5, 12]]").is_err());
+        assert!(ElevationProfile::from_json("[\"flat\"]").is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let profile = ElevationProfile::from_json("[[0, 10], [0.5, 30]]").unwrap();
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ElevationProfile>(&json).unwrap(),
+            profile
+        );
+
+        assert!(serde_json::from_str::<ElevationProfile>(r#"{"samples":[]}"#).is_err());
+        let backwards = r#"{"samples":[[500.0,10.0],[0.0,30.0]]}"#;
+        assert!(serde_json::from_str::<ElevationProfile>(backwards).is_err());
+    }
+}
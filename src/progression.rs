@@ -0,0 +1,376 @@
+//! Zwift levels and a planner for reaching the next ones.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    insider::{Route, RouteRestriction, Sport},
+    physics::Rider,
+    recommend::estimate_route_time,
+    units::Distance,
+};
+
+/// The total XP needed to reach each level from 1 to 50, from Zwift's level
+/// table as mirrored on Zwift Insider. Zwift has since extended levels past
+/// 50; use [`Progression::new`] for those.
+const ZWIFT_LEVEL_XP: [u32; 50] = [
+    0, 750, 1_500, 2_250, 3_250, 4_250, 5_250, 6_250, 7_500, 8_750, 10_000, 11_250, 12_500, 14_000,
+    15_500, 17_000, 19_000, 21_000, 23_000, 25_000, 27_500, 30_000, 32_500, 35_000, 37_500, 46_500,
+    56_500, 67_500, 79_500, 92_500, 106_500, 121_500, 137_500, 154_500, 172_500, 191_500, 211_500,
+    232_500, 254_500, 277_500, 301_500, 326_500, 352_500, 379_500, 407_500, 436_500, 466_500,
+    497_500, 528_000, 561_000,
+];
+
+/// XP earned per km ridden outside of workouts.
+const CYCLING_XP_PER_KM: f64 = 20.0;
+/// XP earned per km run.
+const RUNNING_XP_PER_KM: f64 = 10.0;
+
+/// The XP earned per km of `sport`.
+pub fn xp_per_km(sport: Sport) -> f64 {
+    match sport {
+        Sport::Cycling => CYCLING_XP_PER_KM,
+        Sport::Running => RUNNING_XP_PER_KM,
+    }
+}
+
+/// The XP for completing `route` for the first time: its badge and the
+/// distance, lead-in included.
+pub fn route_xp(route: &Route) -> f64 {
+    let distance = route.distance + route.lead_in_distance;
+    f64::from(route.badge_xp.unwrap_or_default()) + distance.km() * xp_per_km(route.sport)
+}
+
+/// The total XP needed to reach each level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progression {
+    thresholds: Vec<u32>,
+}
+
+impl Default for Progression {
+    fn default() -> Self {
+        Progression {
+            thresholds: ZWIFT_LEVEL_XP.to_vec(),
+        }
+    }
+}
+
+impl Progression {
+    /// A level table, starting at 0 XP for level 1 and increasing.
+    pub fn new(thresholds: Vec<u32>) -> Result<Self> {
+        if thresholds.first() != Some(&0) {
+            return Err(anyhow!("Level 1 must start at 0 XP"));
+        }
+        if thresholds.windows(2).any(|pair| pair[1] <= pair[0]) {
+            return Err(anyhow!("Level XP must increase"));
+        }
+        Ok(Progression { thresholds })
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.thresholds.len() as u32
+    }
+
+    /// The level reached with `xp`.
+    pub fn level(&self, xp: u32) -> u32 {
+        self.thresholds
+            .iter()
+            .take_while(|threshold| **threshold <= xp)
+            .count() as u32
+    }
+
+    /// The total XP needed to reach `level`.
+    pub fn xp_for_level(&self, level: u32) -> Option<u32> {
+        let index = usize::try_from(level).ok()?.checked_sub(1)?;
+        self.thresholds.get(index).copied()
+    }
+}
+
+/// What to ride for a step of a plan.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanActivity<'a> {
+    /// Ride a route, earning its badge
+    Route(&'a Route),
+    /// Ride on flat roads, or any route, for a distance
+    Distance(Distance),
+}
+
+/// A step of a plan, ridden in `week`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep<'a> {
+    /// Numbered from 1
+    pub week: u32,
+    pub activity: PlanActivity<'a>,
+    pub time: Duration,
+    pub xp: u32,
+    /// The level at the end of the step
+    pub level: u32,
+}
+
+/// The rides to reach a target level, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelPlan<'a> {
+    pub steps: Vec<PlanStep<'a>>,
+    pub total_time: Duration,
+    /// The number of weeks the plan takes
+    pub weeks: u32,
+}
+
+/// The lowest level allowed to ride `route`.
+fn required_level(route: &Route) -> u32 {
+    route
+        .restrictions
+        .iter()
+        .filter_map(|restriction| match restriction {
+            RouteRestriction::MinimumLevel(level) => Some(*level),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(1)
+}
+
+/// Splits rides into weeks of `weekly_time`. Routes are ridden in one go, in
+/// the next week if they don't fit in what is left of this one, while
+/// distance is split between weeks.
+struct Schedule {
+    weekly_time: Duration,
+    week: u32,
+    remaining: Duration,
+}
+
+impl Schedule {
+    fn route_week(&mut self, time: Duration) -> u32 {
+        if time > self.remaining && self.remaining < self.weekly_time {
+            self.week += 1;
+            self.remaining = self.weekly_time;
+        }
+        self.remaining = self.remaining.saturating_sub(time);
+        self.week
+    }
+
+    /// Splits `time` into `(week, time)` parts.
+    fn distance_weeks(&mut self, mut time: Duration) -> Vec<(u32, Duration)> {
+        let mut parts = Vec::new();
+        while !time.is_zero() {
+            if self.remaining.is_zero() {
+                self.week += 1;
+                self.remaining = self.weekly_time;
+            }
+            let part = time.min(self.remaining);
+            parts.push((self.week, part));
+            self.remaining -= part;
+            time -= part;
+        }
+        parts
+    }
+}
+
+/// Plans the quickest way for `rider` to get from `current_xp` to
+/// `target_level`, riding `weekly_time` a week.
+///
+/// Route badges not in `completed` are ridden while they earn XP faster than
+/// riding on the flat, best first, once the rider's level allows them. The
+/// rest of the XP comes from distance. Only cycling routes outside of events
+/// are considered.
+pub fn plan_level<'a>(
+    progression: &Progression,
+    routes: &'a [Route],
+    completed: &[String],
+    rider: &Rider,
+    current_xp: u32,
+    target_level: u32,
+    weekly_time: Duration,
+) -> Result<LevelPlan<'a>> {
+    let target_xp = progression.xp_for_level(target_level).ok_or_else(|| {
+        anyhow!(
+            "Level {target_level} is beyond level {}",
+            progression.max_level()
+        )
+    })?;
+    if weekly_time.is_zero() {
+        return Err(anyhow!("No weekly ride time"));
+    }
+
+    // XP per second on the flat, and for each route not yet completed
+    let flat_speed = rider.speed(0.0);
+    let distance_rate = flat_speed / 1000.0 * CYCLING_XP_PER_KM;
+    let mut candidates: Vec<_> = routes
+        .iter()
+        .filter(|route| route.sport == Sport::Cycling && !route.is_event_only())
+        .filter(|route| !completed.iter().any(|name| route.matches_name(name)))
+        .map(|route| {
+            let time = estimate_route_time(route, rider);
+            (route, time, route_xp(route) / time.as_secs_f64())
+        })
+        .filter(|(_, _, rate)| *rate > distance_rate)
+        .collect();
+    candidates.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+
+    let mut schedule = Schedule {
+        weekly_time,
+        week: 1,
+        remaining: weekly_time,
+    };
+    let mut steps = Vec::new();
+    let mut xp = f64::from(current_xp);
+    while xp < f64::from(target_xp) {
+        let level = progression.level(xp as u32);
+        let next_route = candidates
+            .iter()
+            .position(|(route, _, _)| required_level(route) <= level);
+        if let Some(index) = next_route {
+            let (route, time, _) = candidates.remove(index);
+            xp += route_xp(route);
+            steps.push(PlanStep {
+                week: schedule.route_week(time),
+                activity: PlanActivity::Route(route),
+                time,
+                xp: route_xp(route).round() as u32,
+                level: progression.level(xp as u32),
+            });
+            continue;
+        }
+
+        // Ride distance until the target, or until a locked route unlocks
+        let unlock_xp = candidates
+            .iter()
+            .filter_map(|(route, _, _)| progression.xp_for_level(required_level(route)))
+            .min()
+            .unwrap_or(target_xp)
+            .min(target_xp);
+        let needed = f64::from(unlock_xp) - xp;
+        let time = Duration::from_secs_f64(needed / distance_rate);
+        for (week, part) in schedule.distance_weeks(time) {
+            let part_xp = part.as_secs_f64() * distance_rate;
+            xp += part_xp;
+            steps.push(PlanStep {
+                week,
+                activity: PlanActivity::Distance(Distance::from_meters(
+                    part.as_secs_f64() * flat_speed,
+                )),
+                time: part,
+                xp: part_xp.round() as u32,
+                level: progression.level(xp.round() as u32),
+            });
+        }
+        // Guard against rounding leaving a sliver of XP
+        xp = xp.max(f64::from(unlock_xp));
+    }
+
+    Ok(LevelPlan {
+        total_time: steps.iter().map(|step| step.time).sum(),
+        weeks: steps.last().map_or(0, |step| step.week),
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use html_parser::Dom;
+
+    use super::{plan_level, route_xp, PlanActivity, Progression};
+    use crate::{insider::parse_routes, physics::Rider};
+
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+
+    #[test]
+    fn test_progression() {
+        let progression = Progression::default();
+        assert_eq!(progression.max_level(), 50);
+        assert_eq!(progression.level(0), 1);
+        assert_eq!(progression.level(749), 1);
+        assert_eq!(progression.level(750), 2);
+        assert_eq!(progression.level(10_000_000), 50);
+        assert_eq!(progression.xp_for_level(10), Some(8_750));
+        assert_eq!(progression.xp_for_level(25), Some(37_500));
+        assert_eq!(progression.xp_for_level(50), Some(561_000));
+        assert_eq!(progression.xp_for_level(0), None);
+        assert_eq!(progression.xp_for_level(51), None);
+
+        assert!(Progression::new(vec![0, 500, 1_500]).is_ok());
+        assert!(Progression::new(vec![100, 500]).is_err());
+        assert!(Progression::new(vec![0, 500, 500]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_level() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let progression = Progression::default();
+        let rider = Rider::default();
+        let week = Duration::from_secs(3 * 3600);
+
+        // Level 5 to 7 needs 2,000 XP
+        let short = plan_level(&progression, &routes, &[], &rider, 4_000, 7, week).unwrap();
+        let xp: u32 = short.steps.iter().map(|step| step.xp).sum();
+        assert!((2_000..2_600).contains(&xp), "{xp}");
+        assert_eq!(short.steps.last().unwrap().level, 7);
+        assert!(short
+            .steps
+            .iter()
+            .any(|step| matches!(step.activity, PlanActivity::Route(_))));
+        for weekly in (1..=short.weeks).map(|week| {
+            short
+                .steps
+                .iter()
+                .filter(|step| step.week == week)
+                .map(|step| step.time)
+                .sum::<Duration>()
+        }) {
+            assert!(weekly <= week + Duration::from_secs(3600), "{weekly:?}");
+        }
+        assert_eq!(
+            short.total_time,
+            short.steps.iter().map(|step| step.time).sum::<Duration>()
+        );
+
+        // Locked routes are ridden once the rider reaches their level
+        let mut boosted = routes.clone();
+        let road_to_sky = boosted
+            .iter_mut()
+            .find(|route| route.name == "Road to Sky")
+            .unwrap();
+        road_to_sky.badge_xp = Some(5_000);
+        let plan = plan_level(&progression, &boosted, &[], &rider, 4_000, 12, week).unwrap();
+        let index = plan
+            .steps
+            .iter()
+            .position(|step| {
+                matches!(step.activity, PlanActivity::Route(route) if route.name == "Road to Sky")
+            })
+            .unwrap();
+        assert!(index > 0);
+        assert_eq!(plan.steps[index - 1].level, 6);
+        assert!(plan.steps[..index - 1].iter().all(|step| step.level < 6));
+
+        // Completed badges are left out, leaving more distance
+        let completed: Vec<_> = routes.iter().map(|route| route.name.clone()).collect();
+        let distance_only =
+            plan_level(&progression, &routes, &completed, &rider, 4_000, 7, week).unwrap();
+        assert!(distance_only
+            .steps
+            .iter()
+            .all(|step| matches!(step.activity, PlanActivity::Distance(_))));
+        assert!(distance_only.total_time > short.total_time);
+
+        let done = plan_level(&progression, &routes, &[], &rider, 9_000, 5, week).unwrap();
+        assert!(done.steps.is_empty());
+        assert_eq!(done.weeks, 0);
+        assert!(plan_level(&progression, &routes, &[], &rider, 0, 51, week).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_xp() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let road_to_sky = routes.iter().find(|r| r.name == "Road to Sky").unwrap();
+        let expected = f64::from(road_to_sky.badge_xp.unwrap())
+            + (road_to_sky.distance + road_to_sky.lead_in_distance).km() * 20.0;
+        assert_eq!(route_xp(road_to_sky), expected);
+    }
+}
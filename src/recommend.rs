@@ -0,0 +1,197 @@
+//! Recommends routes that fit a target ride duration or a workout.
+
+use std::{fs, ops::RangeInclusive, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    badges::route_names,
+    insider::{Route, Sport},
+    physics::Rider,
+    workout::Workout,
+};
+
+/// A route that fits the target duration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation<'a> {
+    pub route: &'a Route,
+    /// The estimated time to ride the lead-in and the route
+    pub estimated_time: Duration,
+}
+
+/// Reads a completed routes file: one route name per line, where only the
+/// first comma separated field of each line is used.
+pub fn load_completed_routes(path: impl AsRef<Path>) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read completed routes {}", path.display()))?;
+    Ok(route_names(&text).map(str::to_string).collect())
+}
+
+/// Estimates the time for `rider` to ride the lead-in and the route.
+pub fn estimate_route_time(route: &Route, rider: &Rider) -> Duration {
+    rider.estimate_time(
+        route.distance + route.lead_in_distance,
+        route.elevation + route.lead_in_elevation,
+    )
+}
+
+/// The cycling routes not in `completed`, and not event only, that `rider` is
+/// estimated to finish within `duration`, closest to the middle of the range
+/// first and then by badge XP.
+pub fn recommend<'a>(
+    routes: &'a [Route],
+    completed: &[String],
+    rider: &Rider,
+    duration: RangeInclusive<Duration>,
+) -> Vec<Recommendation<'a>> {
+    let target = (*duration.start() + *duration.end()) / 2;
+    let mut recommendations: Vec<_> = routes
+        .iter()
+        .filter(|route| route.sport == Sport::Cycling && !route.is_event_only())
+        .filter(|route| !completed.iter().any(|name| route.matches_name(name)))
+        .map(|route| Recommendation {
+            route,
+            estimated_time: estimate_route_time(route, rider),
+        })
+        .filter(|recommendation| duration.contains(&recommendation.estimated_time))
+        .collect();
+    recommendations.sort_by_key(|recommendation| {
+        (
+            recommendation.estimated_time.abs_diff(target),
+            std::cmp::Reverse(recommendation.route.badge_xp),
+        )
+    });
+    recommendations
+}
+
+/// The rider as they ride `workout` in ERG mode, holding the workout's
+/// average power for a rider with `ftp` in W.
+pub fn workout_rider(workout: &Workout, ftp: f64, rider: &Rider) -> Rider {
+    Rider {
+        watts_per_kg: workout.metrics(ftp).average_power / rider.weight_kg,
+        ..*rider
+    }
+}
+
+/// The cycling routes, other than event only routes, that `rider` is
+/// estimated to finish, lead-in included, within `tolerance` of the end of a
+/// workout lasting `duration`. The routes with the most badge XP come first,
+/// then those finishing closest to the end of the workout.
+pub fn match_workout<'a>(
+    routes: &'a [Route],
+    rider: &Rider,
+    duration: Duration,
+    tolerance: Duration,
+) -> Vec<Recommendation<'a>> {
+    let mut matches: Vec<_> = routes
+        .iter()
+        .filter(|route| route.sport == Sport::Cycling && !route.is_event_only())
+        .map(|route| Recommendation {
+            route,
+            estimated_time: estimate_route_time(route, rider),
+        })
+        .filter(|recommendation| recommendation.estimated_time.abs_diff(duration) <= tolerance)
+        .collect();
+    matches.sort_by_key(|recommendation| {
+        (
+            std::cmp::Reverse(recommendation.route.badge_xp),
+            recommendation.estimated_time.abs_diff(duration),
+        )
+    });
+    matches
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use html_parser::Dom;
+
+    use super::{estimate_route_time, match_workout, recommend, workout_rider};
+    use crate::{insider::parse_routes, physics::Rider, workout::parse_zwo};
+
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+    static OVER_UNDERS_ZWO: &str = include_str!("../fixtures/workouts/over-unders.zwo");
+
+    #[tokio::test]
+    async fn test_recommend() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let rider = Rider::default();
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+
+        let all = recommend(&routes, &[], &rider, Duration::ZERO..=minutes(600));
+        assert!(all.iter().all(|r| r.route.name != "Jungle Loop"));
+        assert!(all.iter().all(|r| r.route.name != "Tour of Fire & Ice"));
+        assert_eq!(all.len(), routes.len() - 2);
+
+        let hour = recommend(&routes, &[], &rider, minutes(20)..=minutes(40));
+        assert!(!hour.is_empty());
+        for recommendation in &hour {
+            assert!(recommendation.estimated_time >= minutes(20));
+            assert!(recommendation.estimated_time <= minutes(40));
+        }
+        let distances: Vec<_> = hour
+            .windows(2)
+            .map(|pair| {
+                (
+                    pair[0].estimated_time.abs_diff(minutes(30)),
+                    pair[1].estimated_time.abs_diff(minutes(30)),
+                )
+            })
+            .collect();
+        assert!(distances.iter().all(|(a, b)| a <= b));
+
+        let completed = vec![hour[0].route.name.to_lowercase()];
+        let remaining = recommend(&routes, &completed, &rider, minutes(20)..=minutes(40));
+        assert_eq!(remaining.len(), hour.len() - 1);
+        assert!(remaining.iter().all(|r| r.route != hour[0].route));
+
+        let road_to_sky = routes.iter().find(|r| r.name == "Road to Sky").unwrap();
+        let tempus_fugit = routes.iter().find(|r| r.name == "Tempus Fugit").unwrap();
+        assert!(
+            estimate_route_time(road_to_sky, &rider) > estimate_route_time(tempus_fugit, &rider)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_match_workout() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let workout = parse_zwo(OVER_UNDERS_ZWO).unwrap();
+        let rider = Rider::default();
+
+        // The workout averages 75% of FTP, 2.49 W/kg for a 75 kg rider with a
+        // 250 W FTP
+        let erg_rider = workout_rider(&workout, 250.0, &rider);
+        assert_eq!(erg_rider.weight_kg, rider.weight_kg);
+        assert!(
+            (erg_rider.watts_per_kg - 2.49).abs() < 0.01,
+            "{}",
+            erg_rider.watts_per_kg
+        );
+
+        let duration = workout.duration();
+        let tolerance = Duration::from_secs(10 * 60);
+        let matches = match_workout(&routes, &erg_rider, duration, tolerance);
+        assert!(!matches.is_empty());
+        for recommendation in &matches {
+            assert!(recommendation.estimated_time.abs_diff(duration) <= tolerance);
+            assert!(!recommendation.route.is_event_only());
+        }
+        assert!(matches
+            .windows(2)
+            .all(|pair| pair[0].route.badge_xp >= pair[1].route.badge_xp));
+
+        let none = match_workout(
+            &routes,
+            &erg_rider,
+            Duration::from_secs(600 * 60),
+            tolerance,
+        );
+        assert!(none.is_empty());
+    }
+}
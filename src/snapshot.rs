@@ -0,0 +1,135 @@
+//! Saves the scraped routes table over time so changes can be found later.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::insider::Route;
+
+/// The routes table as scraped at a point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteSnapshot {
+    pub taken: DateTime<Utc>,
+    pub routes: Vec<Route>,
+}
+
+impl RouteSnapshot {
+    pub fn new(routes: Vec<Route>) -> Self {
+        RouteSnapshot {
+            taken: Utc::now(),
+            routes,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid snapshot {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write snapshot {}", path.display()))
+    }
+}
+
+/// A directory of snapshots, one JSON file each, named by when they were
+/// taken so they sort in time order.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SnapshotStore { dir: dir.into() }
+    }
+
+    /// Saves `snapshot`, returning the path of the new file.
+    pub fn save(&self, snapshot: &RouteSnapshot) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let name = format!("routes-{}.json", snapshot.taken.format("%Y%m%dT%H%M%SZ"));
+        let path = self.dir.join(name);
+        snapshot.save(&path)?;
+        Ok(path)
+    }
+
+    /// The snapshot files in the store, oldest first.
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+        {
+            let path = entry?.path();
+            let is_snapshot = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("routes-") && name.ends_with(".json"));
+            if is_snapshot {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// The most recent snapshot, if there are any.
+    pub fn latest(&self) -> Result<Option<RouteSnapshot>> {
+        match self.list()?.last() {
+            Some(path) => Ok(Some(RouteSnapshot::load(path)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use html_parser::Dom;
+
+    use super::{RouteSnapshot, SnapshotStore};
+    use crate::insider::parse_routes;
+
+    static ROUTES_HTML: &str = include_str!("../fixtures/insider/routes.html");
+
+    #[tokio::test]
+    async fn test_store() {
+        let routes = parse_routes(&Dom::parse(ROUTES_HTML).unwrap())
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("snapshots-{}", std::process::id()));
+        let store = SnapshotStore::new(&dir);
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.latest().unwrap().is_none());
+
+        let older = RouteSnapshot {
+            taken: Utc.with_ymd_and_hms(2026, 9, 1, 8, 0, 0).unwrap(),
+            routes: routes[..3].to_vec(),
+        };
+        let newer = RouteSnapshot {
+            taken: Utc.with_ymd_and_hms(2026, 10, 18, 8, 0, 0).unwrap(),
+            routes,
+        };
+        let newer_path = store.save(&newer).unwrap();
+        let older_path = store.save(&older).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a snapshot").unwrap();
+
+        let listed = store.list().unwrap();
+        let latest = store.latest().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(listed, [older_path, newer_path.clone()]);
+        assert!(newer_path.ends_with("routes-20261018T080000Z.json"));
+        assert_eq!(latest, Some(newer));
+    }
+}
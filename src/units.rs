@@ -0,0 +1,280 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+const METERS_PER_KILOMETER: f64 = 1000.0;
+const METERS_PER_MILE: f64 = 1609.344;
+const METERS_PER_FOOT: f64 = 0.3048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl FromStr for UnitSystem {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "metric" | "km" | "m" => Ok(UnitSystem::Metric),
+            "imperial" | "mi" | "ft" => Ok(UnitSystem::Imperial),
+            _ => Err(anyhow!(
+                "Unknown unit system \"{s}\", expected metric or imperial"
+            )),
+        }
+    }
+}
+
+/// A horizontal distance, stored in meters.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Distance(f64);
+
+impl Distance {
+    pub const ZERO: Distance = Distance(0.0);
+
+    pub fn from_meters(meters: f64) -> Self {
+        Self(meters)
+    }
+
+    pub fn from_km(km: f64) -> Self {
+        Self(km * METERS_PER_KILOMETER)
+    }
+
+    pub fn from_miles(miles: f64) -> Self {
+        Self(miles * METERS_PER_MILE)
+    }
+
+    pub fn meters(&self) -> f64 {
+        self.0
+    }
+
+    pub fn km(&self) -> f64 {
+        self.0 / METERS_PER_KILOMETER
+    }
+
+    pub fn miles(&self) -> f64 {
+        self.0 / METERS_PER_MILE
+    }
+
+    /// Formats the distance to 0.1 km or 0.1 miles.
+    pub fn format(&self, units: UnitSystem) -> String {
+        match units {
+            UnitSystem::Metric => format!("{:.1} km", self.km()),
+            UnitSystem::Imperial => format!("{:.1} mi", self.miles()),
+        }
+    }
+}
+
+impl std::ops::Add for Distance {
+    type Output = Distance;
+
+    fn add(self, other: Distance) -> Distance {
+        Distance(self.0 + other.0)
+    }
+}
+
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(UnitSystem::Metric))
+    }
+}
+
+/// Parses distances as shown on Zwift Insider, e.g. `23.4km`, `14.5 miles` or
+/// the combined `23.4km (14.5 miles)`. The first value with a recognized unit
+/// is used.
+impl FromStr for Distance {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_combined(s, |value, unit| match unit {
+            "km" | "kms" | "kilometer" | "kilometers" | "kilometre" | "kilometres" => {
+                Some(Distance::from_km(value))
+            }
+            "mi" | "mile" | "miles" => Some(Distance::from_miles(value)),
+            "m" | "meter" | "meters" | "metre" | "metres" => Some(Distance::from_meters(value)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("Invalid distance \"{s}\""))
+    }
+}
+
+/// A change in height, stored in meters.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Elevation(f64);
+
+impl Elevation {
+    pub const ZERO: Elevation = Elevation(0.0);
+
+    pub fn from_meters(meters: f64) -> Self {
+        Self(meters)
+    }
+
+    pub fn from_feet(feet: f64) -> Self {
+        Self(feet * METERS_PER_FOOT)
+    }
+
+    pub fn meters(&self) -> f64 {
+        self.0
+    }
+
+    pub fn feet(&self) -> f64 {
+        self.0 / METERS_PER_FOOT
+    }
+
+    /// Formats the elevation to the nearest meter or foot.
+    pub fn format(&self, units: UnitSystem) -> String {
+        match units {
+            UnitSystem::Metric => format!("{:.0} m", self.meters()),
+            UnitSystem::Imperial => format!("{:.0} ft", self.feet()),
+        }
+    }
+}
+
+impl std::ops::Add for Elevation {
+    type Output = Elevation;
+
+    fn add(self, other: Elevation) -> Elevation {
+        Elevation(self.0 + other.0)
+    }
+}
+
+impl fmt::Display for Elevation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(UnitSystem::Metric))
+    }
+}
+
+/// Parses elevations as shown on Zwift Insider, e.g. `412m`, `1,352'`,
+/// `1,352′`, `1352 ft` or the combined `412m (1,352')`.
+impl FromStr for Elevation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_combined(s, |value, unit| match unit {
+            "m" | "meter" | "meters" | "metre" | "metres" => Some(Elevation::from_meters(value)),
+            "'" | "\u{2032}" | "ft" | "foot" | "feet" => Some(Elevation::from_feet(value)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("Invalid elevation \"{s}\""))
+    }
+}
+
+/// Tries each value in a combined form such as `412m (1,352')` in turn.
+fn parse_combined<T>(text: &str, convert: impl Fn(f64, &str) -> Option<T>) -> Option<T> {
+    text.split(['(', ')', '/'])
+        .filter_map(parse_quantity)
+        .find_map(|(value, unit)| convert(value, &unit))
+}
+
+/// Splits a single quantity such as `1,036 m` into its value and lower case
+/// unit.
+fn parse_quantity(text: &str) -> Option<(f64, String)> {
+    let text = text.trim();
+    let unit_start = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(unit_start);
+    let value = parse_number(number)?;
+    Some((value, unit.trim().trim_end_matches('.').to_lowercase()))
+}
+
+/// Parses a number that may contain thousands separators. A comma followed by
+/// exactly three digits is a thousands separator, otherwise it is treated as a
+/// decimal separator, e.g. `1,352` is 1352 and `23,4` is 23.4.
+fn parse_number(number: &str) -> Option<f64> {
+    let groups: Vec<_> = number.split(',').collect();
+    let normalized = match groups.as_slice() {
+        [number] => number.to_string(),
+        [integer, groups @ ..]
+            if groups
+                .iter()
+                .all(|group| group.split('.').next().unwrap().len() == 3) =>
+        {
+            std::iter::once(*integer)
+                .chain(groups.iter().copied())
+                .collect()
+        }
+        [integer, fraction] => format!("{integer}.{fraction}"),
+        _ => return None,
+    };
+    if normalized.is_empty() || normalized.starts_with('.') {
+        return None;
+    }
+    normalized.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distance, Elevation, UnitSystem};
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_parse_distance() {
+        assert_close("23.4km".parse::<Distance>().unwrap().km(), 23.4);
+        assert_close("23.4 km".parse::<Distance>().unwrap().km(), 23.4);
+        assert_close("14.5 miles".parse::<Distance>().unwrap().miles(), 14.5);
+        assert_close("1 mi".parse::<Distance>().unwrap().meters(), 1609.344);
+        assert_close(
+            "23.4km (14.5 miles)".parse::<Distance>().unwrap().km(),
+            23.4,
+        );
+        assert_close("800m".parse::<Distance>().unwrap().km(), 0.8);
+        assert_close("1,234.5 km".parse::<Distance>().unwrap().km(), 1234.5);
+        assert_close("23,4 km".parse::<Distance>().unwrap().km(), 23.4);
+        assert!("-".parse::<Distance>().is_err());
+        assert!("23.4".parse::<Distance>().is_err());
+        assert!("412'".parse::<Distance>().is_err());
+        assert!("".parse::<Distance>().is_err());
+    }
+
+    #[test]
+    fn test_parse_elevation() {
+        assert_close("412m".parse::<Elevation>().unwrap().meters(), 412.0);
+        assert_close("1,352'".parse::<Elevation>().unwrap().feet(), 1352.0);
+        assert_close("1352 ft".parse::<Elevation>().unwrap().feet(), 1352.0);
+        assert_close("3,399\u{2032}".parse::<Elevation>().unwrap().feet(), 3399.0);
+        assert_close(
+            "412m (1,352')".parse::<Elevation>().unwrap().meters(),
+            412.0,
+        );
+        assert_close("(1,352') 412m".parse::<Elevation>().unwrap().feet(), 1352.0);
+        assert_close(
+            "1,036m (3,399')".parse::<Elevation>().unwrap().meters(),
+            1036.0,
+        );
+        assert!("1,35,2m".parse::<Elevation>().is_err());
+        assert!("12km".parse::<Elevation>().is_err());
+    }
+
+    #[test]
+    fn test_conversion_round_trip() {
+        let distance = Distance::from_miles(14.5);
+        assert_close(Distance::from_km(distance.km()).miles(), 14.5);
+        let elevation = Elevation::from_feet(1352.0);
+        assert_close(Elevation::from_meters(elevation.meters()).feet(), 1352.0);
+    }
+
+    #[test]
+    fn test_format() {
+        let distance: Distance = "23.4km (14.5 miles)".parse().unwrap();
+        assert_eq!(distance.format(UnitSystem::Metric), "23.4 km");
+        assert_eq!(distance.format(UnitSystem::Imperial), "14.5 mi");
+        assert_eq!(distance.to_string(), "23.4 km");
+
+        let elevation: Elevation = "412m".parse().unwrap();
+        assert_eq!(elevation.format(UnitSystem::Metric), "412 m");
+        assert_eq!(elevation.format(UnitSystem::Imperial), "1352 ft");
+        assert_eq!(
+            "imperial".parse::<UnitSystem>().unwrap(),
+            UnitSystem::Imperial
+        );
+    }
+}
@@ -0,0 +1,681 @@
+//! Zwift workout files (`.zwo`), as distributed in Zwift Insider's workout
+//! collections.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use html_parser::{Dom, Element, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::{export::escape_xml, html_query, insider::Sport};
+
+mod metrics;
+
+pub use metrics::{WorkoutMetrics, Zone};
+
+/// A workout: its description and the blocks ridden in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workout {
+    pub name: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// The `sportType`, cycling if not given
+    pub sport: Option<Sport>,
+    pub tags: Vec<String>,
+    pub blocks: Vec<Block>,
+    /// Other elements of the file, such as `category`, as name and text
+    pub extra: Vec<(String, String)>,
+}
+
+/// A block of a workout. Powers are fractions of FTP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockKind {
+    Warmup {
+        duration: Duration,
+        power_low: f64,
+        power_high: f64,
+    },
+    SteadyState {
+        duration: Duration,
+        power: f64,
+    },
+    /// `repeat` on and off intervals, the `IntervalsT` element
+    Intervals {
+        repeat: u32,
+        on_duration: Duration,
+        off_duration: Duration,
+        on_power: f64,
+        off_power: f64,
+    },
+    Ramp {
+        duration: Duration,
+        power_low: f64,
+        power_high: f64,
+    },
+    FreeRide {
+        duration: Duration,
+        flat_road: bool,
+    },
+    MaxEffort {
+        duration: Duration,
+    },
+    /// Usually ramps down, from `power_low` to `power_high`
+    Cooldown {
+        duration: Duration,
+        power_low: f64,
+        power_high: f64,
+    },
+    /// An element this module doesn't know, with its attributes kept in
+    /// [`Block::extra`]
+    Other(String),
+}
+
+/// Cadence targets in rpm. `resting` is the target for the off part of
+/// intervals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cadence {
+    pub target: Option<u32>,
+    pub low: Option<u32>,
+    pub high: Option<u32>,
+    pub resting: Option<u32>,
+}
+
+/// A message shown `offset` into a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextEvent {
+    pub offset: Duration,
+    pub message: String,
+    pub duration: Option<Duration>,
+    /// Attributes not parsed into the fields above
+    pub extra: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub cadence: Cadence,
+    pub text_events: Vec<TextEvent>,
+    /// Attributes not parsed into the fields above, e.g. `show_avg`
+    pub extra: BTreeMap<String, String>,
+}
+
+/// An element's attributes, with character references decoded, taken one at
+/// a time so those left over can be kept.
+struct Attributes<'a> {
+    element: &'a str,
+    values: BTreeMap<String, String>,
+}
+
+impl<'a> Attributes<'a> {
+    fn new(element: &'a Element) -> Self {
+        Attributes {
+            element: &element.name,
+            values: element
+                .attributes
+                .iter()
+                .map(|(name, value)| {
+                    let value = value.as_deref().unwrap_or_default();
+                    (name.clone(), html_query::decode_entities(value))
+                })
+                .collect(),
+        }
+    }
+
+    /// Removes the attribute called `name`, ignoring case as some tools write
+    /// `duration` for `Duration`.
+    fn take(&mut self, name: &str) -> Option<String> {
+        let key = self
+            .values
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(name))?
+            .clone();
+        self.values.remove(&key)
+    }
+
+    fn number<T: FromStr>(&mut self, name: &str) -> Result<Option<T>> {
+        match self.take(name) {
+            Some(value) => match value.trim().parse() {
+                Ok(number) => Ok(Some(number)),
+                Err(_) => Err(anyhow!("Invalid {name} \"{value}\" in {}", self.element)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn required<T: FromStr>(&mut self, name: &str) -> Result<T> {
+        self.number(name)?
+            .ok_or_else(|| anyhow!("{} has no {name}", self.element))
+    }
+
+    fn optional_duration(&mut self, name: &str) -> Result<Option<Duration>> {
+        match self.number::<f64>(name)? {
+            Some(seconds) => Duration::try_from_secs_f64(seconds)
+                .map(Some)
+                .map_err(|_| anyhow!("Invalid {name} {seconds} in {}", self.element)),
+            None => Ok(None),
+        }
+    }
+
+    fn duration(&mut self, name: &str) -> Result<Duration> {
+        self.optional_duration(name)?
+            .ok_or_else(|| anyhow!("{} has no {name}", self.element))
+    }
+
+    /// Takes a power range, where a single `Power` means a constant power.
+    fn power_range(&mut self) -> Result<(f64, f64)> {
+        let power: Option<f64> = self.number("Power")?;
+        let low = self
+            .number("PowerLow")?
+            .or(power)
+            .ok_or_else(|| anyhow!("{} has no PowerLow", self.element))?;
+        let high = self.number("PowerHigh")?.unwrap_or(low);
+        Ok((low, high))
+    }
+}
+
+/// The text of an element, with character references decoded but line breaks
+/// kept.
+fn element_text(element: &Element) -> String {
+    let text: Vec<_> = element
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    html_query::decode_entities(&text.join(" "))
+}
+
+fn child_elements(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|child| match child {
+        Node::Element(element) => Some(element),
+        _ => None,
+    })
+}
+
+fn parse_text_event(element: &Element) -> Result<TextEvent> {
+    let mut attributes = Attributes::new(element);
+    Ok(TextEvent {
+        offset: attributes.duration("timeoffset")?,
+        message: attributes.take("message").unwrap_or_default(),
+        duration: attributes.optional_duration("duration")?,
+        extra: attributes.values,
+    })
+}
+
+fn parse_block(element: &Element) -> Result<Block> {
+    let mut attributes = Attributes::new(element);
+    let kind = match element.name.to_lowercase().as_str() {
+        "warmup" => {
+            let duration = attributes.duration("Duration")?;
+            let (power_low, power_high) = attributes.power_range()?;
+            BlockKind::Warmup {
+                duration,
+                power_low,
+                power_high,
+            }
+        }
+        "steadystate" => BlockKind::SteadyState {
+            duration: attributes.duration("Duration")?,
+            power: match attributes.number("Power")? {
+                Some(power) => power,
+                None => attributes.required("PowerLow")?,
+            },
+        },
+        "intervalst" => BlockKind::Intervals {
+            repeat: attributes.number("Repeat")?.unwrap_or(1),
+            on_duration: attributes.duration("OnDuration")?,
+            off_duration: attributes.duration("OffDuration")?,
+            on_power: attributes.required("OnPower")?,
+            off_power: attributes.required("OffPower")?,
+        },
+        "ramp" => {
+            let duration = attributes.duration("Duration")?;
+            let (power_low, power_high) = attributes.power_range()?;
+            BlockKind::Ramp {
+                duration,
+                power_low,
+                power_high,
+            }
+        }
+        "freeride" => BlockKind::FreeRide {
+            duration: attributes.duration("Duration")?,
+            flat_road: attributes
+                .take("FlatRoad")
+                .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+        },
+        "maxeffort" => BlockKind::MaxEffort {
+            duration: attributes.duration("Duration")?,
+        },
+        "cooldown" => {
+            let duration = attributes.duration("Duration")?;
+            let (power_low, power_high) = attributes.power_range()?;
+            BlockKind::Cooldown {
+                duration,
+                power_low,
+                power_high,
+            }
+        }
+        _ => BlockKind::Other(element.name.clone()),
+    };
+    let cadence = Cadence {
+        target: attributes.number("Cadence")?,
+        low: attributes.number("CadenceLow")?,
+        high: attributes.number("CadenceHigh")?,
+        resting: attributes.number("CadenceResting")?,
+    };
+    let text_events = child_elements(element)
+        .filter(|child| child.name.eq_ignore_ascii_case("textevent"))
+        .map(parse_text_event)
+        .collect::<Result<_>>()?;
+    Ok(Block {
+        kind,
+        cadence,
+        text_events,
+        extra: attributes.values,
+    })
+}
+
+/// Parses a `.zwo` workout file.
+pub fn parse_zwo(xml: &str) -> Result<Workout> {
+    let dom = Dom::parse(xml)?;
+    let root = dom
+        .children
+        .iter()
+        .find_map(|child| match child {
+            Node::Element(element) if element.name == "workout_file" => Some(element),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No <workout_file> element"))?;
+
+    let mut name = None;
+    let mut workout = Workout {
+        name: String::new(),
+        author: None,
+        description: None,
+        sport: None,
+        tags: Vec::new(),
+        blocks: Vec::new(),
+        extra: Vec::new(),
+    };
+    let mut has_blocks = false;
+    for element in child_elements(root) {
+        match element.name.as_str() {
+            "name" => name = Some(element_text(element)),
+            "author" => workout.author = Some(element_text(element)),
+            "description" => workout.description = Some(element_text(element)),
+            "sportType" => workout.sport = Some(element_text(element).parse()?),
+            "tags" => {
+                workout.tags = child_elements(element)
+                    .filter_map(|tag| Attributes::new(tag).take("name"))
+                    .collect()
+            }
+            "workout" => {
+                has_blocks = true;
+                workout.blocks = child_elements(element)
+                    .map(parse_block)
+                    .collect::<Result<_>>()?;
+            }
+            _ => workout
+                .extra
+                .push((element.name.clone(), element_text(element))),
+        }
+    }
+
+    workout.name = name.ok_or_else(|| anyhow!("Workout has no name"))?;
+    if has_blocks {
+        Ok(workout)
+    } else {
+        Err(anyhow!("\"{}\" has no <workout> element", workout.name))
+    }
+}
+
+/// Formats seconds as written in `.zwo` files, without a fraction when whole.
+fn seconds(duration: Duration) -> String {
+    duration.as_secs_f64().to_string()
+}
+
+fn write_block(zwo: &mut String, block: &Block) {
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut push = |name: &str, value: String| attributes.push((name.to_string(), value));
+    let name = match &block.kind {
+        BlockKind::Warmup {
+            duration,
+            power_low,
+            power_high,
+        }
+        | BlockKind::Ramp {
+            duration,
+            power_low,
+            power_high,
+        }
+        | BlockKind::Cooldown {
+            duration,
+            power_low,
+            power_high,
+        } => {
+            push("Duration", seconds(*duration));
+            push("PowerLow", power_low.to_string());
+            push("PowerHigh", power_high.to_string());
+            match block.kind {
+                BlockKind::Warmup { .. } => "Warmup",
+                BlockKind::Ramp { .. } => "Ramp",
+                _ => "Cooldown",
+            }
+        }
+        BlockKind::SteadyState { duration, power } => {
+            push("Duration", seconds(*duration));
+            push("Power", power.to_string());
+            "SteadyState"
+        }
+        BlockKind::Intervals {
+            repeat,
+            on_duration,
+            off_duration,
+            on_power,
+            off_power,
+        } => {
+            push("Repeat", repeat.to_string());
+            push("OnDuration", seconds(*on_duration));
+            push("OffDuration", seconds(*off_duration));
+            push("OnPower", on_power.to_string());
+            push("OffPower", off_power.to_string());
+            "IntervalsT"
+        }
+        BlockKind::FreeRide {
+            duration,
+            flat_road,
+        } => {
+            push("Duration", seconds(*duration));
+            if *flat_road {
+                push("FlatRoad", "1".to_string());
+            }
+            "FreeRide"
+        }
+        BlockKind::MaxEffort { duration } => {
+            push("Duration", seconds(*duration));
+            "MaxEffort"
+        }
+        BlockKind::Other(name) => name,
+    };
+    let cadence = [
+        ("Cadence", block.cadence.target),
+        ("CadenceLow", block.cadence.low),
+        ("CadenceHigh", block.cadence.high),
+        ("CadenceResting", block.cadence.resting),
+    ];
+    for (name, value) in cadence {
+        if let Some(value) = value {
+            push(name, value.to_string());
+        }
+    }
+    for (name, value) in &block.extra {
+        push(name, value.clone());
+    }
+
+    let _ = write!(zwo, "        <{name}");
+    for (name, value) in attributes {
+        let _ = write!(zwo, " {name}=\"{}\"", escape_xml(&value));
+    }
+    if block.text_events.is_empty() {
+        zwo.push_str("/>\n");
+        return;
+    }
+    zwo.push_str(">\n");
+    for event in &block.text_events {
+        let _ = write!(
+            zwo,
+            "            <textevent timeoffset=\"{}\" message=\"{}\"",
+            seconds(event.offset),
+            escape_xml(&event.message)
+        );
+        if let Some(duration) = event.duration {
+            let _ = write!(zwo, " duration=\"{}\"", seconds(duration));
+        }
+        for (name, value) in &event.extra {
+            let _ = write!(zwo, " {name}=\"{}\"", escape_xml(value));
+        }
+        zwo.push_str("/>\n");
+    }
+    let _ = writeln!(zwo, "        </{name}>");
+}
+
+impl Workout {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let xml = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read workout {}", path.display()))?;
+        parse_zwo(&xml).with_context(|| format!("Invalid workout {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_zwo())
+            .with_context(|| format!("Failed to write workout {}", path.display()))
+    }
+
+    /// Writes the workout as a `.zwo` file, which [`parse_zwo`] reads back
+    /// to an equal `Workout`.
+    pub fn to_zwo(&self) -> String {
+        let mut zwo = String::new();
+        zwo.push_str("<workout_file>\n");
+        if let Some(author) = &self.author {
+            let _ = writeln!(zwo, "    <author>{}</author>", escape_xml(author));
+        }
+        let _ = writeln!(zwo, "    <name>{}</name>", escape_xml(&self.name));
+        if let Some(description) = &self.description {
+            let _ = writeln!(
+                zwo,
+                "    <description>{}</description>",
+                escape_xml(description)
+            );
+        }
+        if let Some(sport) = self.sport {
+            let sport = match sport {
+                Sport::Cycling => "bike",
+                Sport::Running => "run",
+            };
+            let _ = writeln!(zwo, "    <sportType>{sport}</sportType>");
+        }
+        for (name, text) in &self.extra {
+            let _ = writeln!(zwo, "    <{name}>{}</{name}>", escape_xml(text));
+        }
+        if !self.tags.is_empty() {
+            zwo.push_str("    <tags>\n");
+            for tag in &self.tags {
+                let _ = writeln!(zwo, "        <tag name=\"{}\"/>", escape_xml(tag));
+            }
+            zwo.push_str("    </tags>\n");
+        }
+        zwo.push_str("    <workout>\n");
+        for block in &self.blocks {
+            write_block(&mut zwo, block);
+        }
+        zwo.push_str("    </workout>\n</workout_file>\n");
+        zwo
+    }
+}
+
+/// Loads the `.zwo` files in `dir`, in file name order. Each result is paired
+/// with its path, so one invalid file doesn't stop the others loading.
+pub fn load_workouts(dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, Result<Workout>)>> {
+    let dir = dir.as_ref();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("zwo"))
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let workout = Workout::load(&path);
+            (path, workout)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use super::{load_workouts, parse_zwo, BlockKind, Cadence, TextEvent};
+    use crate::insider::Sport;
+
+    static OVER_UNDERS_ZWO: &str = include_str!("../fixtures/workouts/over-unders.zwo");
+
+    #[test]
+    fn test_parse_zwo() {
+        let workout = parse_zwo(OVER_UNDERS_ZWO).unwrap();
+        assert_eq!(workout.name, "Over-Unders & Sprints");
+        assert_eq!(workout.author.as_deref(), Some("Zwift Insider"));
+        assert_eq!(
+            workout.description.as_deref(),
+            Some(
+                "Threshold over-unders followed by seated sprints.\n\
+                 Keep your cadence high on the \"overs\"."
+            )
+        );
+        assert_eq!(workout.sport, Some(Sport::Cycling));
+        assert_eq!(workout.tags, ["THRESHOLD", "INTERVALS"]);
+        assert_eq!(
+            workout.extra,
+            [("category".to_string(), "Threshold".to_string())]
+        );
+        assert_eq!(workout.blocks.len(), 8);
+
+        let warmup = &workout.blocks[0];
+        assert_eq!(
+            warmup.kind,
+            BlockKind::Warmup {
+                duration: Duration::from_secs(600),
+                power_low: 0.25,
+                power_high: 0.75,
+            }
+        );
+        assert_eq!(warmup.cadence.target, Some(85));
+
+        assert_eq!(
+            workout.blocks[1].text_events[1],
+            TextEvent {
+                offset: Duration::from_secs(240),
+                message: "One minute to go".to_string(),
+                duration: Some(Duration::from_secs(10)),
+                extra: BTreeMap::new(),
+            }
+        );
+        assert_eq!(
+            workout.blocks[2].kind,
+            BlockKind::Intervals {
+                repeat: 4,
+                on_duration: Duration::from_secs(120),
+                off_duration: Duration::from_secs(60),
+                on_power: 1.05,
+                off_power: 0.95,
+            }
+        );
+        assert_eq!(
+            workout.blocks[2].cadence,
+            Cadence {
+                target: Some(95),
+                resting: Some(85),
+                ..Cadence::default()
+            }
+        );
+
+        let steady = &workout.blocks[4];
+        assert_eq!(
+            (steady.cadence.low, steady.cadence.high),
+            (Some(85), Some(95))
+        );
+        assert_eq!(steady.extra["show_avg"], "1");
+        assert_eq!(
+            workout.blocks[5].kind,
+            BlockKind::FreeRide {
+                duration: Duration::from_secs(300),
+                flat_road: true,
+            }
+        );
+        assert_eq!(
+            workout.blocks[5].text_events[0].message,
+            "Spin easy <60% effort"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let workout = parse_zwo(OVER_UNDERS_ZWO).unwrap();
+        let zwo = workout.to_zwo();
+        assert_eq!(parse_zwo(&zwo).unwrap(), workout);
+        assert!(zwo.contains(
+            "        <SteadyState Duration=\"420\" Power=\"0.9\" CadenceLow=\"85\" CadenceHigh=\"95\" show_avg=\"1\"/>\n"
+        ));
+        assert!(zwo.contains("message=\"Spin easy &lt;60% effort\"/>"));
+
+        // Unknown blocks and alternative spellings are kept
+        let workout = parse_zwo(
+            "<workout_file><name>Odd</name><workout>\
+             <Warmup duration=\"300\" Power=\"0.5\"/>\
+             <SolidState Duration=\"60\" Power=\"0.7\"/>\
+             </workout></workout_file>",
+        )
+        .unwrap();
+        assert_eq!(
+            workout.blocks[0].kind,
+            BlockKind::Warmup {
+                duration: Duration::from_secs(300),
+                power_low: 0.5,
+                power_high: 0.5,
+            }
+        );
+        assert_eq!(
+            workout.blocks[1].kind,
+            BlockKind::Other("SolidState".to_string())
+        );
+        assert_eq!(parse_zwo(&workout.to_zwo()).unwrap(), workout);
+    }
+
+    #[test]
+    fn test_load_workouts() {
+        let dir = std::env::temp_dir().join(format!("workouts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.zwo"), OVER_UNDERS_ZWO).unwrap();
+        std::fs::write(dir.join("a.ZWO"), "<workout_file></workout_file>").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a workout").unwrap();
+
+        let workouts = load_workouts(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(workouts.len(), 2);
+        assert!(workouts[0].0.ends_with("a.ZWO"));
+        assert!(workouts[0].1.is_err());
+        assert_eq!(
+            workouts[1].1.as_ref().unwrap().name,
+            "Over-Unders & Sprints"
+        );
+    }
+
+    #[test]
+    fn test_parse_zwo_errors() {
+        assert!(parse_zwo("<html><body>Not a workout</body></html>").is_err());
+        assert!(parse_zwo("<workout_file><workout></workout></workout_file>").is_err());
+        assert!(parse_zwo(
+            "<workout_file><name>Broken</name><workout>\
+             <SteadyState Duration=\"ten\" Power=\"0.5\"/>\
+             </workout></workout_file>"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{BlockKind, Workout};
+
+/// The power assumed for free ride blocks, which have no target, as a
+/// fraction of FTP.
+const FREE_RIDE_POWER: f64 = 0.6;
+/// The power assumed for max effort blocks, as a fraction of FTP.
+const MAX_EFFORT_POWER: f64 = 1.5;
+/// The rolling average used for normalized power, in seconds.
+const NORMALIZED_POWER_WINDOW: usize = 30;
+
+/// Zwift's workout power zones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Zone {
+    Recovery,
+    Endurance,
+    Tempo,
+    Threshold,
+    Vo2Max,
+    Anaerobic,
+}
+
+impl Zone {
+    pub const ALL: [Zone; 6] = [
+        Zone::Recovery,
+        Zone::Endurance,
+        Zone::Tempo,
+        Zone::Threshold,
+        Zone::Vo2Max,
+        Zone::Anaerobic,
+    ];
+
+    /// The zone of `power`, a fraction of FTP.
+    pub fn for_power(power: f64) -> Zone {
+        if power < 0.6 {
+            Zone::Recovery
+        } else if power < 0.76 {
+            Zone::Endurance
+        } else if power < 0.9 {
+            Zone::Tempo
+        } else if power < 1.05 {
+            Zone::Threshold
+        } else if power < 1.19 {
+            Zone::Vo2Max
+        } else {
+            Zone::Anaerobic
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Z{}", self.index() + 1)
+    }
+}
+
+/// The training load of a workout for a rider with a given FTP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkoutMetrics {
+    pub duration: Duration,
+    /// In W
+    pub average_power: f64,
+    /// In W
+    pub normalized_power: f64,
+    pub intensity_factor: f64,
+    pub tss: f64,
+    time_in_zones: [Duration; 6],
+}
+
+impl WorkoutMetrics {
+    pub fn time_in_zone(&self, zone: Zone) -> Duration {
+        self.time_in_zones[zone.index()]
+    }
+}
+
+/// A stretch of a workout ridden at a power changing linearly from `start`
+/// to `end`, as fractions of FTP.
+struct Piece {
+    duration: Duration,
+    start: f64,
+    end: f64,
+}
+
+impl Piece {
+    fn steady(duration: Duration, power: f64) -> Self {
+        Piece {
+            duration,
+            start: power,
+            end: power,
+        }
+    }
+}
+
+fn pieces(kind: &BlockKind) -> Vec<Piece> {
+    match *kind {
+        BlockKind::Warmup {
+            duration,
+            power_low,
+            power_high,
+        }
+        | BlockKind::Ramp {
+            duration,
+            power_low,
+            power_high,
+        }
+        | BlockKind::Cooldown {
+            duration,
+            power_low,
+            power_high,
+        } => vec![Piece {
+            duration,
+            start: power_low,
+            end: power_high,
+        }],
+        BlockKind::SteadyState { duration, power } => vec![Piece::steady(duration, power)],
+        BlockKind::Intervals {
+            repeat,
+            on_duration,
+            off_duration,
+            on_power,
+            off_power,
+        } => (0..repeat)
+            .flat_map(|_| {
+                [
+                    Piece::steady(on_duration, on_power),
+                    Piece::steady(off_duration, off_power),
+                ]
+            })
+            .collect(),
+        BlockKind::FreeRide { duration, .. } => vec![Piece::steady(duration, FREE_RIDE_POWER)],
+        BlockKind::MaxEffort { duration } => vec![Piece::steady(duration, MAX_EFFORT_POWER)],
+        BlockKind::Other(_) => Vec::new(),
+    }
+}
+
+impl Workout {
+    /// The total duration of the known blocks.
+    pub fn duration(&self) -> Duration {
+        self.blocks
+            .iter()
+            .flat_map(|block| pieces(&block.kind))
+            .map(|piece| piece.duration)
+            .sum()
+    }
+
+    /// The target power for each second of the workout, as fractions of FTP,
+    /// taken at the middle of the second. A workout that doesn't last a whole
+    /// number of seconds ends with a sample for the part second. Free ride
+    /// blocks are counted at 60% and max efforts at 150%, and unknown blocks
+    /// are left out.
+    pub fn power_samples(&self) -> Vec<f64> {
+        let pieces: Vec<_> = self
+            .blocks
+            .iter()
+            .flat_map(|block| pieces(&block.kind))
+            .filter(|piece| !piece.duration.is_zero())
+            .collect();
+        let duration = self.duration();
+        let seconds = duration.as_nanos().div_ceil(1_000_000_000) as u64;
+
+        // Sample the workout as a whole, so pieces that don't last a whole
+        // number of seconds share the seconds they overlap
+        let mut samples = Vec::new();
+        let mut index = 0;
+        let mut piece_start = 0.0;
+        for second in 0..seconds {
+            let end = duration.min(Duration::from_secs(second + 1)).as_secs_f64();
+            let time = (second as f64 + end) / 2.0;
+            while index + 1 < pieces.len()
+                && piece_start + pieces[index].duration.as_secs_f64() <= time
+            {
+                piece_start += pieces[index].duration.as_secs_f64();
+                index += 1;
+            }
+            let piece = &pieces[index];
+            let fraction = ((time - piece_start) / piece.duration.as_secs_f64()).min(1.0);
+            samples.push(piece.start + (piece.end - piece.start) * fraction);
+        }
+        samples
+    }
+
+    /// Calculates the workout's training load for a rider with `ftp` in W.
+    pub fn metrics(&self, ftp: f64) -> WorkoutMetrics {
+        let samples = self.power_samples();
+        let duration = self.duration();
+        let mut time_in_zones = [Duration::ZERO; 6];
+        let mut work = 0.0;
+        for (second, power) in samples.iter().enumerate() {
+            // Each sample covers a second, except a part second at the end
+            let time = (duration - Duration::from_secs(second as u64)).min(Duration::from_secs(1));
+            time_in_zones[Zone::for_power(*power).index()] += time;
+            work += power * time.as_secs_f64();
+        }
+
+        let average = if duration.is_zero() {
+            0.0
+        } else {
+            work / duration.as_secs_f64()
+        };
+        // Normalized power is the fourth root of the mean fourth power of the
+        // 30 second rolling average
+        let normalized = if samples.len() < NORMALIZED_POWER_WINDOW {
+            average
+        } else {
+            let rolling: Vec<f64> = samples
+                .windows(NORMALIZED_POWER_WINDOW)
+                .map(|window| window.iter().sum::<f64>() / NORMALIZED_POWER_WINDOW as f64)
+                .collect();
+            (rolling.iter().map(|power| power.powi(4)).sum::<f64>() / rolling.len() as f64)
+                .powf(0.25)
+        };
+
+        WorkoutMetrics {
+            duration,
+            average_power: average * ftp,
+            normalized_power: normalized * ftp,
+            intensity_factor: normalized,
+            tss: duration.as_secs_f64() / 3600.0 * normalized * normalized * 100.0,
+            time_in_zones,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Zone;
+    use crate::workout::parse_zwo;
+
+    static OVER_UNDERS_ZWO: &str = include_str!("../../fixtures/workouts/over-unders.zwo");
+
+    fn approx_eq(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn test_zones() {
+        assert_eq!(Zone::for_power(0.5), Zone::Recovery);
+        assert_eq!(Zone::for_power(0.75), Zone::Endurance);
+        assert_eq!(Zone::for_power(0.9), Zone::Threshold);
+        assert_eq!(Zone::for_power(1.2), Zone::Anaerobic);
+        assert_eq!(Zone::Vo2Max.to_string(), "Z5");
+    }
+
+    #[test]
+    fn test_steady_metrics() {
+        // An hour at FTP is 100 TSS by definition
+        let workout = parse_zwo(
+            "<workout_file><name>FTP Test</name><workout>\
+             <SteadyState Duration=\"3600\" Power=\"1.0\"/>\
+             </workout></workout_file>",
+        )
+        .unwrap();
+        let metrics = workout.metrics(250.0);
+        assert_eq!(metrics.duration, Duration::from_secs(3600));
+        assert!(approx_eq(metrics.normalized_power, 250.0, 1e-9));
+        assert!(approx_eq(metrics.intensity_factor, 1.0, 1e-9));
+        assert!(approx_eq(metrics.tss, 100.0, 1e-9));
+        assert_eq!(
+            metrics.time_in_zone(Zone::Threshold),
+            Duration::from_secs(3600)
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        let workout = parse_zwo(OVER_UNDERS_ZWO).unwrap();
+        // 600 + 300 + 4 * 180 + 180 + 420 + 300 + 20 + 480 seconds
+        assert_eq!(workout.duration(), Duration::from_secs(3020));
+        assert_eq!(workout.power_samples().len(), 3020);
+
+        let metrics = workout.metrics(200.0);
+        assert_eq!(
+            Zone::ALL
+                .iter()
+                .map(|zone| metrics.time_in_zone(*zone))
+                .sum::<Duration>(),
+            metrics.duration
+        );
+        // The unders, the top of the ramp and the steady state are threshold,
+        // the overs just into VO2 max
+        assert_eq!(
+            metrics.time_in_zone(Zone::Threshold),
+            Duration::from_secs(4 * 60 + 45 + 420)
+        );
+        assert_eq!(
+            metrics.time_in_zone(Zone::Vo2Max),
+            Duration::from_secs(4 * 120)
+        );
+        assert_eq!(
+            metrics.time_in_zone(Zone::Anaerobic),
+            Duration::from_secs(20)
+        );
+        assert!(metrics.normalized_power > metrics.average_power);
+        assert!(approx_eq(
+            metrics.intensity_factor,
+            metrics.normalized_power / 200.0,
+            1e-9
+        ));
+        assert!(approx_eq(
+            metrics.tss,
+            3020.0 / 3600.0 * metrics.intensity_factor.powi(2) * 100.0,
+            1e-9
+        ));
+        assert!((40.0..70.0).contains(&metrics.tss), "{}", metrics.tss);
+    }
+
+    #[test]
+    fn test_part_second_metrics() {
+        // 6 * 1.5 + 0.5 seconds, sampled at the middle of each second
+        let workout = parse_zwo(
+            "<workout_file><name>Microbursts</name><workout>\
+             <IntervalsT Repeat=\"3\" OnDuration=\"1.5\" OffDuration=\"1.5\" OnPower=\"1.5\" OffPower=\"0.5\"/>\
+             <SteadyState Duration=\"0.5\" Power=\"1.0\"/>\
+             </workout></workout_file>",
+        )
+        .unwrap();
+        assert_eq!(workout.duration(), Duration::from_millis(9500));
+        assert_eq!(
+            workout.power_samples(),
+            [1.5, 0.5, 0.5, 1.5, 0.5, 0.5, 1.5, 0.5, 0.5, 1.0]
+        );
+
+        let metrics = workout.metrics(200.0);
+        assert_eq!(metrics.duration, Duration::from_millis(9500));
+        assert_eq!(
+            Zone::ALL
+                .iter()
+                .map(|zone| metrics.time_in_zone(*zone))
+                .sum::<Duration>(),
+            metrics.duration
+        );
+        assert_eq!(
+            metrics.time_in_zone(Zone::Anaerobic),
+            Duration::from_secs(3)
+        );
+        assert_eq!(metrics.time_in_zone(Zone::Recovery), Duration::from_secs(6));
+        assert_eq!(
+            metrics.time_in_zone(Zone::Threshold),
+            Duration::from_millis(500)
+        );
+        assert!(approx_eq(
+            metrics.average_power,
+            (3.0 * 1.5 + 6.0 * 0.5 + 0.5) / 9.5 * 200.0,
+            1e-9
+        ));
+    }
+}
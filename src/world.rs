@@ -0,0 +1,245 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+/// A latitude and longitude range, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    const fn new(latitude: (f64, f64), longitude: (f64, f64)) -> Self {
+        BoundingBox {
+            min_latitude: latitude.0,
+            max_latitude: latitude.1,
+            min_longitude: longitude.0,
+            max_longitude: longitude.1,
+        }
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_latitude + self.max_latitude) / 2.0,
+            (self.min_longitude + self.max_longitude) / 2.0,
+        )
+    }
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        (self.min_latitude..=self.max_latitude).contains(&latitude)
+            && (self.min_longitude..=self.max_longitude).contains(&longitude)
+    }
+}
+
+/// A Zwift map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum World {
+    Watopia,
+    London,
+    NewYork,
+    Innsbruck,
+    Richmond,
+    Yorkshire,
+    France,
+    Paris,
+    MakuriIslands,
+    Scotland,
+    CritCity,
+    Bologna,
+}
+
+impl World {
+    pub const ALL: [World; 12] = [
+        World::Watopia,
+        World::London,
+        World::NewYork,
+        World::Innsbruck,
+        World::Richmond,
+        World::Yorkshire,
+        World::France,
+        World::Paris,
+        World::MakuriIslands,
+        World::Scotland,
+        World::CritCity,
+        World::Bologna,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            World::Watopia => "Watopia",
+            World::London => "London",
+            World::NewYork => "New York",
+            World::Innsbruck => "Innsbruck",
+            World::Richmond => "Richmond",
+            World::Yorkshire => "Yorkshire",
+            World::France => "France",
+            World::Paris => "Paris",
+            World::MakuriIslands => "Makuri Islands",
+            World::Scotland => "Scotland",
+            World::CritCity => "Crit City",
+            World::Bologna => "Bologna",
+        }
+    }
+
+    /// Guest worlds are only available on the days the guest world calendar
+    /// schedules them. Watopia is always available.
+    pub fn is_guest_world(&self) -> bool {
+        !matches!(self, World::Watopia | World::CritCity | World::Bologna)
+    }
+
+    /// Worlds that can only be ridden in events.
+    pub fn is_event_only(&self) -> bool {
+        matches!(self, World::CritCity | World::Bologna)
+    }
+
+    /// Where the world is set, or `None` for fictional worlds.
+    pub fn location(&self) -> Option<&'static str> {
+        match self {
+            World::Watopia | World::MakuriIslands | World::CritCity => None,
+            World::London => Some("London, England"),
+            World::NewYork => Some("New York City, USA"),
+            World::Innsbruck => Some("Innsbruck, Austria"),
+            World::Richmond => Some("Richmond, Virginia, USA"),
+            World::Yorkshire => Some("Harrogate, England"),
+            World::France => Some("Provence, France"),
+            World::Paris => Some("Paris, France"),
+            World::Scotland => Some("Glasgow, Scotland"),
+            World::Bologna => Some("Bologna, Italy"),
+        }
+    }
+
+    /// The approximate area covered by the coordinates Zwift records for rides
+    /// in the world. Fictional worlds are placed in the ocean, e.g. Watopia
+    /// near the Solomon Islands.
+    pub fn bounding_box(&self) -> BoundingBox {
+        match self {
+            World::Watopia => BoundingBox::new((-11.74, -11.62), (166.87, 167.03)),
+            World::London => BoundingBox::new((51.46, 51.53), (-0.20, -0.06)),
+            World::NewYork => BoundingBox::new((40.76, 40.80), (-73.99, -73.94)),
+            World::Innsbruck => BoundingBox::new((47.20, 47.30), (11.35, 11.45)),
+            World::Richmond => BoundingBox::new((37.52, 37.57), (-77.48, -77.41)),
+            World::Yorkshire => BoundingBox::new((53.95, 54.01), (-1.60, -1.50)),
+            World::France => BoundingBox::new((-21.76, -21.64), (166.13, 166.26)),
+            World::Paris => BoundingBox::new((48.85, 48.88), (2.27, 2.32)),
+            World::MakuriIslands => BoundingBox::new((-10.85, -10.73), (165.75, 165.86)),
+            World::Scotland => BoundingBox::new((55.62, 55.68), (-5.28, -5.20)),
+            World::CritCity => BoundingBox::new((-10.40, -10.37), (165.79, 165.82)),
+            World::Bologna => BoundingBox::new((44.48, 44.50), (11.29, 11.32)),
+        }
+    }
+
+    /// The year the world was added to Zwift.
+    pub fn released(&self) -> u16 {
+        match self {
+            World::Watopia => 2014,
+            World::Richmond => 2015,
+            World::London => 2016,
+            World::Innsbruck => 2017,
+            World::NewYork => 2018,
+            World::Yorkshire => 2019,
+            World::France => 2020,
+            World::Paris => 2020,
+            World::CritCity => 2019,
+            World::Bologna => 2019,
+            World::MakuriIslands => 2021,
+            World::Scotland => 2023,
+        }
+    }
+}
+
+impl fmt::Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Parses the world names used on Zwift Insider, which vary between pages,
+/// e.g. "NYC", "Makuri", "Neokyo" or "UCI Worlds Richmond".
+impl FromStr for World {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        let world = match normalized.as_str() {
+            "watopia" => World::Watopia,
+            "london" => World::London,
+            "newyork" | "newyorkcity" | "nyc" | "ny" | "centralpark" => World::NewYork,
+            "innsbruck" => World::Innsbruck,
+            "richmond" | "ucirichmond" | "uciworldsrichmond" => World::Richmond,
+            "yorkshire" | "harrogate" | "uciworldsyorkshire" | "uciworldsharrogate" => {
+                World::Yorkshire
+            }
+            "france" | "provence" => World::France,
+            "paris" => World::Paris,
+            "makuri" | "makuriislands" | "makuriisland" | "neokyo" | "urukazi" | "yumezi" => {
+                World::MakuriIslands
+            }
+            "scotland" | "glasgow" => World::Scotland,
+            "critcity" => World::CritCity,
+            "bologna" | "bolognatt" | "bolognatimetrial" => World::Bologna,
+            _ => return Err(anyhow!("Unknown Zwift world \"{s}\"")),
+        };
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::World;
+
+    #[test]
+    fn test_parse() {
+        for world in World::ALL {
+            assert_eq!(world.name().parse::<World>().unwrap(), world);
+            assert_eq!(world.to_string().parse::<World>().unwrap(), world);
+        }
+        assert_eq!("NYC".parse::<World>().unwrap(), World::NewYork);
+        assert_eq!("new-york".parse::<World>().unwrap(), World::NewYork);
+        assert_eq!("MAKURI".parse::<World>().unwrap(), World::MakuriIslands);
+        assert_eq!("Neokyo".parse::<World>().unwrap(), World::MakuriIslands);
+        assert_eq!(
+            "UCI Worlds Richmond".parse::<World>().unwrap(),
+            World::Richmond
+        );
+        assert_eq!(" Crit City ".parse::<World>().unwrap(), World::CritCity);
+        assert!("Mars".parse::<World>().is_err());
+        assert!("".parse::<World>().is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert!(!World::Watopia.is_guest_world());
+        assert!(World::London.is_guest_world());
+        assert!(World::MakuriIslands.is_guest_world());
+        assert!(World::Bologna.is_event_only());
+        assert!(!World::Bologna.is_guest_world());
+        assert_eq!(World::Watopia.location(), None);
+        assert_eq!(World::Scotland.location(), Some("Glasgow, Scotland"));
+        assert_eq!(World::Watopia.released(), 2014);
+        assert_eq!(World::France.released(), 2020);
+        assert_eq!(World::Paris.released(), 2020);
+        assert_eq!(World::MakuriIslands.released(), 2021);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        for world in World::ALL {
+            let bounds = world.bounding_box();
+            assert!(bounds.min_latitude < bounds.max_latitude, "{world}");
+            assert!(bounds.min_longitude < bounds.max_longitude, "{world}");
+            let (latitude, longitude) = bounds.center();
+            assert!(bounds.contains(latitude, longitude));
+        }
+        let watopia = World::Watopia.bounding_box();
+        assert!(watopia.contains(-11.64, 166.95));
+        assert!(!watopia.contains(51.5, -0.1));
+    }
+}